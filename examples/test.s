@@ -1,4 +1,4 @@
-fun println do
+fun println (s ->) do
   "\n" + print
 end
 
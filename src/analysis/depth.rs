@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use crate::compiler::{effect::StackEffect, instruction::InstructionKind, program::Program};
+
+use super::diagnostic::Diagnostic;
+
+/// Tracks the abstract depth of the value stack through every compiled block,
+/// following `Jump`/`JumpIfFalse` edges and the known arity of each instruction.
+///
+/// Depths are relative to the depth on entry to the block, so the values a
+/// function consumes from its caller show up as negative depths. Only the entry
+/// point starts from a known empty stack, which is what makes an underflow there
+/// definite; other blocks are summarized as a `StackEffect` and checked at their
+/// call sites instead.
+pub struct DepthAnalyzer<'a> {
+    program: &'a Program,
+    labels: Vec<String>,
+
+    summaries: HashMap<usize, Option<StackEffect>>,
+    in_progress: Vec<usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> DepthAnalyzer<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Self {
+            program,
+            labels: program.block_labels(),
+
+            summaries: HashMap::new(),
+            in_progress: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn analyze(mut self) -> Vec<Diagnostic> {
+        for block in 0..self.program.blocks.len() {
+            self.summary(block);
+        }
+
+        self.diagnostics
+    }
+
+    fn block_name(&self, block: usize) -> String {
+        format!("`{}`", self.labels[block])
+    }
+
+    fn summary(&mut self, block: usize) -> Option<StackEffect> {
+        if let Some(summary) = self.summaries.get(&block) {
+            return *summary;
+        }
+
+        // Recursive calls have an unknown effect until the block is summarized.
+        if self.in_progress.contains(&block) {
+            return None;
+        }
+
+        self.in_progress.push(block);
+        let summary = self.analyze_block(block);
+        self.in_progress.pop();
+
+        self.summaries.insert(block, summary);
+        summary
+    }
+
+    fn analyze_block(&mut self, block: usize) -> Option<StackEffect> {
+        let program = self.program;
+        let instructions = &program.blocks[block].instructions;
+        let is_entry_point = block == program.entry_point;
+
+        let mut depths: Vec<Option<isize>> = vec![None; instructions.len()];
+        let mut worklist = vec![(0, 0, 0)];
+
+        let mut lowest = 0;
+        let mut exit_depth = None;
+        let mut known = true;
+
+        while let Some((ip, depth, from)) = worklist.pop() {
+            if ip >= instructions.len() {
+                self.record_exit(block, &mut exit_depth, depth, from);
+                continue;
+            }
+
+            match depths[ip] {
+                Some(expected) if expected == depth => continue,
+                Some(expected) => {
+                    let location = &instructions[from].location;
+                    let message = if ip <= from {
+                        format!(
+                            "Loop body in {} changes the stack depth by {:+}",
+                            self.block_name(block),
+                            depth - expected
+                        )
+                    } else {
+                        format!(
+                            "Branches in {} join with different stack depths ({} and {})",
+                            self.block_name(block),
+                            expected,
+                            depth
+                        )
+                    };
+
+                    self.diagnostics.push(if ip <= from {
+                        Diagnostic::error(message, location)
+                    } else {
+                        Diagnostic::warning(message, location)
+                    });
+
+                    continue;
+                }
+                None => depths[ip] = Some(depth),
+            }
+
+            let instruction = &instructions[ip];
+            let effect = match &instruction.kind {
+                InstructionKind::Call(callee) => self.summary(*callee),
+                InstructionKind::CallNative(index) => {
+                    let native = self.program.natives.get(*index);
+                    Some(StackEffect::new(native.inputs, native.outputs))
+                }
+                // The fields of the variant are pushed when following the jump table below.
+                InstructionKind::Match { .. } => Some(StackEffect::new(1, 0)),
+                kind => kind.stack_effect(),
+            };
+
+            // Past a call with an unknown effect the depth can't be tracked any further.
+            let effect = match effect {
+                Some(effect) => effect,
+                None => {
+                    known = false;
+                    continue;
+                }
+            };
+
+            let mut popped = depth - effect.inputs as isize;
+            if popped < lowest {
+                lowest = popped;
+
+                if is_entry_point && popped < 0 {
+                    let operation = match instruction.kind {
+                        InstructionKind::Call(callee) => self.block_name(callee),
+                        InstructionKind::CallNative(index) => {
+                            format!("`{}`", self.program.natives.get(index).name)
+                        }
+                        _ => format!("`{}`", instruction.kind),
+                    };
+
+                    self.diagnostics.push(Diagnostic::error(
+                        format!(
+                            "Stack underflow: {} needs {} value(s) but only {} are available",
+                            operation,
+                            effect.inputs,
+                            depth.max(0)
+                        ),
+                        &instruction.location,
+                    ));
+
+                    lowest = 0;
+                    popped = 0;
+                }
+            }
+
+            // Nothing runs after an instruction or call that never returns.
+            if effect.diverges {
+                continue;
+            }
+
+            let depth = popped + effect.outputs as isize;
+
+            match instruction.kind {
+                InstructionKind::Match { type_id, ref table } => {
+                    let variants = &program.enums[type_id].variants;
+
+                    for (variant, target) in variants.iter().zip(table.iter()) {
+                        if let Some(target) = target {
+                            worklist.push((*target, depth + variant.fields as isize, ip));
+                        }
+                    }
+                }
+                // The handler runs with the error value pushed.
+                InstructionKind::PushHandler(target) => {
+                    worklist.push((target, depth + 1, ip));
+                    worklist.push((ip + 1, depth, ip));
+                }
+                InstructionKind::Jump(target) => worklist.push((target, depth, ip)),
+                InstructionKind::JumpIfFalse(target) | InstructionKind::JumpIfTrue(target) => {
+                    worklist.push((target, depth, ip));
+                    worklist.push((ip + 1, depth, ip));
+                }
+                InstructionKind::Return | InstructionKind::Halt => {
+                    self.record_exit(block, &mut exit_depth, depth, ip)
+                }
+                _ => worklist.push((ip + 1, depth, ip)),
+            }
+        }
+
+        if !known {
+            return None;
+        }
+
+        let inputs = -lowest;
+
+        // A block with no reachable exit never returns.
+        match exit_depth {
+            Some(exit_depth) => Some(StackEffect::new(
+                inputs as usize,
+                (exit_depth - lowest) as usize,
+            )),
+            None => Some(StackEffect::diverging(inputs as usize)),
+        }
+    }
+
+    fn record_exit(
+        &mut self,
+        block: usize,
+        exit_depth: &mut Option<isize>,
+        depth: isize,
+        from: usize,
+    ) {
+        match exit_depth {
+            Some(expected) if *expected != depth => {
+                let location = &self.program.blocks[block].instructions[from].location;
+
+                self.diagnostics.push(Diagnostic::warning(
+                    format!(
+                        "{} returns with different stack depths ({} and {})",
+                        self.block_name(block),
+                        expected,
+                        depth
+                    ),
+                    location,
+                ));
+            }
+            Some(_) => {}
+            None => *exit_depth = Some(depth),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::Display;
+
+use crate::token::location::Location;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Location,
+}
+
+impl Diagnostic {
+    pub fn warning(message: String, location: &Location) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            location: location.clone(),
+        }
+    }
+
+    pub fn error(message: String, location: &Location) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            location: location.clone(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        write!(f, "{}: {} at {}", severity, self.message, self.location)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod depth;
+pub mod diagnostic;
+pub mod types;
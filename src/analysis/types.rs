@@ -0,0 +1,664 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compiler::{
+    instruction::{Instruction, InstructionKind},
+    program::Program,
+};
+
+use super::diagnostic::Diagnostic;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    String,
+    Closure,
+    Record,
+    Variant,
+    List,
+    Unknown,
+}
+
+impl Type {
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float | Type::Unknown)
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        if self == other {
+            *self
+        } else {
+            Type::Unknown
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "Int"),
+            Type::Float => write!(f, "Float"),
+            Type::Bool => write!(f, "Bool"),
+            Type::String => write!(f, "String"),
+            Type::Closure => write!(f, "Closure"),
+            Type::Record => write!(f, "Record"),
+            Type::Variant => write!(f, "Variant"),
+            Type::List => write!(f, "List"),
+            Type::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// The abstract value stack of a block. `values` sits on top of the `consumed`
+/// values the block has popped from its caller, whose types are unknown.
+#[derive(Debug, Clone, PartialEq)]
+struct TypeStack {
+    consumed: usize,
+    values: Vec<Type>,
+    locals: Vec<Type>,
+}
+
+impl TypeStack {
+    fn new() -> Self {
+        Self {
+            consumed: 0,
+            values: Vec::new(),
+            locals: Vec::new(),
+        }
+    }
+
+    fn depth(&self) -> isize {
+        self.values.len() as isize - self.consumed as isize
+    }
+
+    fn pop(&mut self) -> Type {
+        match self.values.pop() {
+            Some(value) => value,
+            None => {
+                self.consumed += 1;
+                Type::Unknown
+            }
+        }
+    }
+
+    fn push(&mut self, value: Type) {
+        self.values.push(value);
+    }
+
+    /// Merges the state of another path into this one, returning `None` when the
+    /// depths disagree (which the depth analysis reports on its own).
+    fn merge(&self, other: &Self) -> Option<Self> {
+        if self.depth() != other.depth() || self.locals.len() != other.locals.len() {
+            return None;
+        }
+
+        let consumed = self.consumed.max(other.consumed);
+        let align = |stack: &Self| {
+            let mut values = vec![Type::Unknown; consumed - stack.consumed];
+            values.extend(stack.values.iter().copied());
+            values
+        };
+
+        let values = align(self)
+            .iter()
+            .zip(align(other).iter())
+            .map(|(left, right)| left.meet(right))
+            .collect();
+
+        let locals = self
+            .locals
+            .iter()
+            .zip(other.locals.iter())
+            .map(|(left, right)| left.meet(right))
+            .collect();
+
+        Some(Self {
+            consumed,
+            values,
+            locals,
+        })
+    }
+}
+
+/// What a caller needs to know about a block: how many values it pops and the
+/// types of the values it leaves behind.
+#[derive(Debug, Clone)]
+struct Summary {
+    inputs: usize,
+    outputs: Vec<Type>,
+}
+
+/// Infers the types of stack slots through every compiled block using the type
+/// signature of each instruction, reporting operations that can never succeed.
+pub struct TypeChecker<'a> {
+    program: &'a Program,
+
+    summaries: HashMap<usize, Option<Summary>>,
+    in_progress: Vec<usize>,
+
+    reported: HashSet<(usize, usize)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Self {
+            program,
+
+            summaries: HashMap::new(),
+            in_progress: Vec::new(),
+
+            reported: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self) -> Vec<Diagnostic> {
+        for block in 0..self.program.blocks.len() {
+            self.summary(block);
+        }
+
+        self.diagnostics
+    }
+
+    fn summary(&mut self, block: usize) -> Option<Summary> {
+        if let Some(summary) = self.summaries.get(&block) {
+            return summary.clone();
+        }
+
+        if self.in_progress.contains(&block) {
+            return None;
+        }
+
+        self.in_progress.push(block);
+        let summary = self.check_block(block);
+        self.in_progress.pop();
+
+        self.summaries.insert(block, summary.clone());
+        summary
+    }
+
+    fn report(&mut self, block: usize, ip: usize, message: String, instruction: &Instruction) {
+        if self.reported.insert((block, ip)) {
+            self.diagnostics
+                .push(Diagnostic::error(message, &instruction.location));
+        }
+    }
+
+    fn check_block(&mut self, block: usize) -> Option<Summary> {
+        let program = self.program;
+        let instructions = &program.blocks[block].instructions;
+
+        let mut states: Vec<Option<TypeStack>> = vec![None; instructions.len()];
+        let mut worklist = vec![(0, TypeStack::new())];
+
+        let mut exit: Option<TypeStack> = None;
+        let mut known = true;
+
+        while let Some((ip, state)) = worklist.pop() {
+            if ip >= instructions.len() {
+                exit = merge_exit(exit, state);
+                continue;
+            }
+
+            let state = match &states[ip] {
+                Some(previous) => match previous.merge(&state) {
+                    Some(merged) if &merged == previous => continue,
+                    Some(merged) => merged,
+                    None => continue,
+                },
+                None => state,
+            };
+
+            states[ip] = Some(state.clone());
+
+            let instruction = &instructions[ip];
+            let mut state = state;
+
+            if !self.step(block, ip, instruction, &mut state) {
+                known = false;
+                continue;
+            }
+
+            match instruction.kind {
+                InstructionKind::Match { type_id, ref table } => {
+                    let variants = &program.enums[type_id].variants;
+
+                    for (variant, target) in variants.iter().zip(table.iter()) {
+                        if let Some(target) = target {
+                            let mut state = state.clone();
+                            state
+                                .values
+                                .extend(std::iter::repeat_n(Type::Unknown, variant.fields));
+
+                            worklist.push((*target, state));
+                        }
+                    }
+                }
+                // The handler runs with the error value pushed.
+                InstructionKind::PushHandler(target) => {
+                    let mut handler = state.clone();
+                    handler.push(Type::Unknown);
+
+                    worklist.push((target, handler));
+                    worklist.push((ip + 1, state));
+                }
+                InstructionKind::Jump(target) => worklist.push((target, state)),
+                InstructionKind::JumpIfFalse(target) | InstructionKind::JumpIfTrue(target) => {
+                    worklist.push((target, state.clone()));
+                    worklist.push((ip + 1, state));
+                }
+                InstructionKind::Return | InstructionKind::Halt => {
+                    exit = merge_exit(exit, state);
+                }
+                // Errors leave the block without returning.
+                InstructionKind::Panic | InstructionKind::Throw => {}
+                _ => worklist.push((ip + 1, state)),
+            }
+        }
+
+        match (known, exit) {
+            (true, Some(exit)) => Some(Summary {
+                inputs: exit.consumed,
+                outputs: exit.values,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Applies the type signature of `instruction` to `state`. Returns `false`
+    /// when the effect on the stack can't be known statically.
+    fn step(
+        &mut self,
+        block: usize,
+        ip: usize,
+        instruction: &Instruction,
+        state: &mut TypeStack,
+    ) -> bool {
+        match &instruction.kind {
+            InstructionKind::NoOp
+            | InstructionKind::Patch
+            | InstructionKind::Jump(_)
+            | InstructionKind::PushHandler(_)
+            | InstructionKind::PopHandler
+            | InstructionKind::DebugStack
+            | InstructionKind::Return
+            | InstructionKind::Halt => {}
+
+            InstructionKind::LoadI64(_) => state.push(Type::Int),
+            InstructionKind::LoadF64(_) => state.push(Type::Float),
+            InstructionKind::LoadBool(_) => state.push(Type::Bool),
+            InstructionKind::LoadConstant(_) => state.push(Type::String),
+            // Globals can be assigned values of any type from anywhere.
+            InstructionKind::Get(_) | InstructionKind::LoadGlobal(_) => state.push(Type::Unknown),
+            InstructionKind::MakeClosure { .. } => state.push(Type::Closure),
+
+            InstructionKind::Bind(count) => {
+                let mut values = (0..*count).map(|_| state.pop()).collect::<Vec<_>>();
+                values.reverse();
+
+                state.locals.extend(values);
+            }
+            InstructionKind::Unbind(count) => {
+                let length = state.locals.len().saturating_sub(*count);
+                state.locals.truncate(length);
+            }
+            InstructionKind::SetLocal(slot) => {
+                let value = state.pop();
+
+                if let Some(local) = state.locals.get_mut(*slot) {
+                    *local = value;
+                }
+            }
+            InstructionKind::GetLocal(slot) => {
+                let value = state.locals.get(*slot).copied().unwrap_or(Type::Unknown);
+                state.push(value);
+            }
+
+            InstructionKind::Add => {
+                let right = state.pop();
+                let left = state.pop();
+
+                let result = match (left, right) {
+                    (Type::String, Type::String) => Type::String,
+                    (Type::String, Type::Unknown) | (Type::Unknown, Type::String) => Type::String,
+                    _ => self.arithmetic("add", left, right, block, ip, instruction),
+                };
+
+                state.push(result);
+            }
+            InstructionKind::Sub
+            | InstructionKind::Mul
+            | InstructionKind::Mod
+            | InstructionKind::Pow => {
+                let right = state.pop();
+                let left = state.pop();
+
+                let operation = match instruction.kind {
+                    InstructionKind::Sub => "subtract",
+                    InstructionKind::Mul => "multiply",
+                    InstructionKind::Mod => "take the remainder of",
+                    _ => "exponentiate",
+                };
+
+                let result = self.arithmetic(operation, left, right, block, ip, instruction);
+                state.push(result);
+            }
+            InstructionKind::Div => {
+                let right = state.pop();
+                let left = state.pop();
+
+                self.arithmetic("divide", left, right, block, ip, instruction);
+                state.push(Type::Float);
+            }
+            InstructionKind::Neg => {
+                let value = state.pop();
+
+                if !value.is_numeric() {
+                    self.report(block, ip, format!("cannot negate {}", value), instruction);
+                }
+
+                state.push(value);
+            }
+            InstructionKind::Not => {
+                let value = state.pop();
+
+                if !matches!(value, Type::Bool | Type::Unknown) {
+                    self.report(block, ip, format!("cannot negate {}", value), instruction);
+                }
+
+                state.push(Type::Bool);
+            }
+            InstructionKind::And | InstructionKind::Or => {
+                let right = state.pop();
+                let left = state.pop();
+
+                if ![left, right]
+                    .iter()
+                    .all(|value| matches!(value, Type::Bool | Type::Unknown))
+                {
+                    let operation = match instruction.kind {
+                        InstructionKind::And => "and",
+                        _ => "or",
+                    };
+
+                    self.report(
+                        block,
+                        ip,
+                        format!("cannot {} {} and {}", operation, left, right),
+                        instruction,
+                    );
+                }
+
+                state.push(Type::Bool);
+            }
+            InstructionKind::MakeRecord { fields, .. } => {
+                for _ in 0..*fields {
+                    state.pop();
+                }
+
+                state.push(Type::Record);
+            }
+            InstructionKind::GetField(field) | InstructionKind::SetField(field) => {
+                let value = match instruction.kind {
+                    InstructionKind::SetField(_) => Some(state.pop()),
+                    _ => None,
+                };
+
+                let record = state.pop();
+                if !matches!(record, Type::Record | Type::Unknown) {
+                    self.report(
+                        block,
+                        ip,
+                        format!("cannot access field `{}` of {}", field, record),
+                        instruction,
+                    );
+                }
+
+                state.push(match value {
+                    Some(_) => Type::Record,
+                    None => Type::Unknown,
+                });
+            }
+            InstructionKind::MakeVariant { fields, .. } => {
+                for _ in 0..*fields {
+                    state.pop();
+                }
+
+                state.push(Type::Variant);
+            }
+            InstructionKind::Match { .. } => {
+                let value = state.pop();
+
+                if !matches!(value, Type::Variant | Type::Unknown) {
+                    self.report(block, ip, format!("cannot match on {}", value), instruction);
+                }
+            }
+            InstructionKind::ListNew => state.push(Type::List),
+            InstructionKind::ListPush | InstructionKind::ListGet | InstructionKind::ListSet => {
+                let value = match instruction.kind {
+                    InstructionKind::ListGet => None,
+                    _ => Some(state.pop()),
+                };
+
+                let index = match instruction.kind {
+                    InstructionKind::ListPush => Type::Int,
+                    _ => state.pop(),
+                };
+
+                let list = state.pop();
+                if !matches!(list, Type::List | Type::Unknown) {
+                    self.report(
+                        block,
+                        ip,
+                        format!("cannot index into {}", list),
+                        instruction,
+                    );
+                } else if !matches!(index, Type::Int | Type::Unknown) {
+                    self.report(
+                        block,
+                        ip,
+                        format!("list index must be an Int, found {}", index),
+                        instruction,
+                    );
+                }
+
+                state.push(match value {
+                    Some(_) => Type::List,
+                    None => Type::Unknown,
+                });
+            }
+            InstructionKind::Length => {
+                let value = state.pop();
+
+                if !matches!(value, Type::List | Type::String | Type::Unknown) {
+                    self.report(
+                        block,
+                        ip,
+                        format!("cannot take the length of {}", value),
+                        instruction,
+                    );
+                }
+
+                state.push(Type::Int);
+            }
+            InstructionKind::ToString => {
+                state.pop();
+                state.push(Type::String);
+            }
+            // At the end of the input these push `false` instead.
+            InstructionKind::ReadLine | InstructionKind::ReadInt => state.push(Type::Unknown),
+            InstructionKind::ReadAll => state.push(Type::String),
+            InstructionKind::FileRead
+            | InstructionKind::FileExists
+            | InstructionKind::FileLines
+            | InstructionKind::DirList => {
+                let path = state.pop();
+
+                if !matches!(path, Type::String | Type::Unknown) {
+                    self.report(
+                        block,
+                        ip,
+                        format!("path must be a String, found {}", path),
+                        instruction,
+                    );
+                }
+
+                state.push(match instruction.kind {
+                    InstructionKind::FileRead => Type::String,
+                    InstructionKind::FileExists => Type::Bool,
+                    _ => Type::List,
+                });
+            }
+            InstructionKind::Args => state.push(Type::List),
+            InstructionKind::EnvGet => {
+                let name = state.pop();
+
+                if !matches!(name, Type::String | Type::Unknown) {
+                    self.report(
+                        block,
+                        ip,
+                        format!("variable name must be a String, found {}", name),
+                        instruction,
+                    );
+                }
+
+                // Unset variables push `false` instead.
+                state.push(Type::Unknown);
+            }
+            InstructionKind::FileWrite | InstructionKind::FileAppend => {
+                state.pop();
+                state.pop();
+            }
+            InstructionKind::Panic | InstructionKind::Throw => {
+                state.pop();
+            }
+            InstructionKind::Equals | InstructionKind::NotEquals => {
+                state.pop();
+                state.pop();
+                state.push(Type::Bool);
+            }
+            InstructionKind::LessThan
+            | InstructionKind::LessThanEquals
+            | InstructionKind::GreaterThan
+            | InstructionKind::GreaterThanEquals => {
+                let right = state.pop();
+                let left = state.pop();
+
+                if !left.is_numeric() || !right.is_numeric() {
+                    self.report(
+                        block,
+                        ip,
+                        format!("cannot compare {} and {}", left, right),
+                        instruction,
+                    );
+                }
+
+                state.push(Type::Bool);
+            }
+
+            InstructionKind::Dup => {
+                let value = state.pop();
+                state.push(value);
+                state.push(value);
+            }
+            InstructionKind::Drop | InstructionKind::Print | InstructionKind::StoreGlobal(_) => {
+                state.pop();
+            }
+            InstructionKind::Swap => {
+                let right = state.pop();
+                let left = state.pop();
+                state.push(right);
+                state.push(left);
+            }
+            InstructionKind::Over => {
+                let right = state.pop();
+                let left = state.pop();
+                state.push(left);
+                state.push(right);
+                state.push(left);
+            }
+
+            InstructionKind::JumpIfFalse(_) | InstructionKind::JumpIfTrue(_) => {
+                let value = state.pop();
+
+                if !matches!(value, Type::Bool | Type::Unknown) {
+                    self.report(
+                        block,
+                        ip,
+                        format!("condition must be a Bool, found {}", value),
+                        instruction,
+                    );
+                }
+            }
+
+            InstructionKind::Call(callee) => match self.summary(*callee) {
+                Some(summary) => {
+                    for _ in 0..summary.inputs {
+                        state.pop();
+                    }
+
+                    state.values.extend(summary.outputs);
+                }
+                None => return false,
+            },
+            // Native words declare their arity but not the types of their values.
+            InstructionKind::CallNative(index) => {
+                let native = self.program.natives.get(*index);
+
+                for _ in 0..native.inputs {
+                    state.pop();
+                }
+
+                for _ in 0..native.outputs {
+                    state.push(Type::Unknown);
+                }
+            }
+            InstructionKind::CallClosure => {
+                let value = state.pop();
+
+                if !matches!(value, Type::Closure | Type::Unknown) {
+                    self.report(block, ip, format!("cannot apply {}", value), instruction);
+                }
+
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn arithmetic(
+        &mut self,
+        operation: &str,
+        left: Type,
+        right: Type,
+        block: usize,
+        ip: usize,
+        instruction: &Instruction,
+    ) -> Type {
+        match (left, right) {
+            (Type::Int, Type::Int) => Type::Int,
+            (Type::Float, Type::Float | Type::Int) | (Type::Int, Type::Float) => Type::Float,
+            _ if left.is_numeric() && right.is_numeric() => Type::Unknown,
+            _ => {
+                self.report(
+                    block,
+                    ip,
+                    format!("cannot {} {} and {}", operation, left, right),
+                    instruction,
+                );
+
+                Type::Unknown
+            }
+        }
+    }
+}
+
+fn merge_exit(exit: Option<TypeStack>, state: TypeStack) -> Option<TypeStack> {
+    match exit {
+        Some(exit) => exit.merge(&state).or(Some(exit)),
+        None => Some(state),
+    }
+}
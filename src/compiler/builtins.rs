@@ -0,0 +1,54 @@
+use super::instruction::InstructionKind;
+
+/// Maps the name of a builtin word (or the internal name the parser gives an
+/// operator, e.g. `__plus` for `+`) to the instruction implementing it.
+pub fn builtin_instruction(name: &str) -> Option<InstructionKind> {
+    let kind = match name {
+        "__plus" => InstructionKind::Add,
+        "__minus" => InstructionKind::Sub,
+        "__mult" => InstructionKind::Mul,
+        "__div" => InstructionKind::Div,
+        "__mod" => InstructionKind::Mod,
+        "__not" => InstructionKind::Not,
+        "__gt" => InstructionKind::GreaterThan,
+        "__gte" => InstructionKind::GreaterThanEquals,
+        "__lt" => InstructionKind::LessThan,
+        "__lte" => InstructionKind::LessThanEquals,
+        "__and" => InstructionKind::And,
+        "__or" => InstructionKind::Or,
+        "__eqeq" => InstructionKind::Equals,
+        "__noteq" => InstructionKind::NotEquals,
+        "()__quit" => InstructionKind::Halt,
+
+        "dup" => InstructionKind::Dup,
+        "drop" => InstructionKind::Drop,
+        "print" => InstructionKind::Print,
+        "swap" => InstructionKind::Swap,
+        "over" => InstructionKind::Over,
+        "???" => InstructionKind::DebugStack,
+        "apply" => InstructionKind::CallClosure,
+
+        "list" => InstructionKind::ListNew,
+        "push" => InstructionKind::ListPush,
+        "nth" => InstructionKind::ListGet,
+        "set-nth" => InstructionKind::ListSet,
+        "length" => InstructionKind::Length,
+        "to-string" => InstructionKind::ToString,
+        "panic" => InstructionKind::Panic,
+        "throw" => InstructionKind::Throw,
+        "read-line" => InstructionKind::ReadLine,
+        "read-all" => InstructionKind::ReadAll,
+        "read-int" => InstructionKind::ReadInt,
+        "file-read" => InstructionKind::FileRead,
+        "file-write" => InstructionKind::FileWrite,
+        "file-append" => InstructionKind::FileAppend,
+        "file-exists" => InstructionKind::FileExists,
+        "file-lines" => InstructionKind::FileLines,
+        "dir-list" => InstructionKind::DirList,
+        "args" => InstructionKind::Args,
+        "env-get" => InstructionKind::EnvGet,
+        _ => return None,
+    };
+
+    Some(kind)
+}
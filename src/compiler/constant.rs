@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use crate::{
+    parser::ast::{AstNode, Block},
+    token::location::Location,
+};
+
+use super::error::ResolverError;
+
+/// The value of a `const` declaration, computed while resolving the program
+/// and inlined wherever the constant is used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl std::fmt::Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constant::I64(i) => write!(f, "{}", i),
+            Constant::F64(fl) => write!(f, "{}", fl),
+            Constant::Bool(b) => write!(f, "{}", b),
+            Constant::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// Evaluates the body of a `const` declaration. Only literals, previously
+/// declared constants, operators and the basic stack words are allowed, and
+/// the body must leave exactly one value.
+pub fn evaluate(
+    name: &str,
+    body: &Block,
+    location: &Location,
+    constants: &HashMap<String, Constant>,
+) -> Result<Constant, ResolverError> {
+    let invalid = |reason: String, location: &Location| ResolverError::InvalidConstant {
+        name: name.to_string(),
+        reason,
+        location: location.clone(),
+    };
+
+    let mut stack = Vec::new();
+
+    for node in &body.nodes {
+        let arity = match node {
+            AstNode::Identifier(word, _) | AstNode::FunctionCall { name: word, .. } => {
+                match word.as_str() {
+                    "__not" | "dup" | "drop" => 1,
+                    "swap" | "over" => 2,
+                    word if word.starts_with("__") => 2,
+                    _ => 0,
+                }
+            }
+            _ => 0,
+        };
+
+        if stack.len() < arity {
+            return Err(invalid(
+                "not enough values on the stack".to_string(),
+                node.location(),
+            ));
+        }
+
+        let mut operands = stack.split_off(stack.len() - arity);
+
+        match node {
+            AstNode::IntegerLiteral(i, _) => stack.push(Constant::I64(*i)),
+            AstNode::FloatLiteral(f, _) => stack.push(Constant::F64(*f)),
+            AstNode::BooleanLiteral(b, _) => stack.push(Constant::Bool(*b)),
+            AstNode::StringLiteral(s, _) => stack.push(Constant::String(s.clone())),
+            AstNode::Identifier(word, location)
+            | AstNode::FunctionCall {
+                name: word,
+                location,
+            } => match word.as_str() {
+                "dup" => {
+                    let value = operands.remove(0);
+                    stack.push(value.clone());
+                    stack.push(value);
+                }
+                "drop" => {}
+                "swap" => {
+                    operands.swap(0, 1);
+                    stack.extend(operands);
+                }
+                "over" => {
+                    let first = operands[0].clone();
+                    stack.extend(operands);
+                    stack.push(first);
+                }
+                "__not" => match operands.remove(0) {
+                    Constant::Bool(b) => stack.push(Constant::Bool(!b)),
+                    value => return Err(invalid(format!("cannot negate {}", value), location)),
+                },
+                operator if arity == 2 => {
+                    let right = operands.pop().unwrap();
+                    let left = operands.pop().unwrap();
+
+                    match apply(operator, left, right) {
+                        Ok(value) => stack.push(value),
+                        Err(reason) => return Err(invalid(reason, location)),
+                    }
+                }
+                word => match constants.get(word) {
+                    Some(value) => stack.push(value.clone()),
+                    None => return Err(invalid(format!("`{}` is not a constant", word), location)),
+                },
+            },
+            node => {
+                return Err(invalid(
+                    "only literals, constants and operators can be used".to_string(),
+                    node.location(),
+                ))
+            }
+        }
+    }
+
+    match stack.len() {
+        1 => Ok(stack.remove(0)),
+        count => Err(invalid(
+            format!("the body must leave exactly one value, found {}", count),
+            location,
+        )),
+    }
+}
+
+fn apply(operator: &str, left: Constant, right: Constant) -> Result<Constant, String> {
+    let mismatch = |left: &Constant, right: &Constant| {
+        Err(format!(
+            "cannot apply `{}` to {} and {}",
+            symbol(operator),
+            left,
+            right
+        ))
+    };
+
+    // As when running the program, dividing by the integer zero is an error
+    // and dividing two integers gives a float.
+    if matches!(operator, "__div" | "__mod") && right == Constant::I64(0) {
+        return Err("division by zero".to_string());
+    }
+
+    let value = match (operator, &left, &right) {
+        ("__plus", Constant::String(a), Constant::String(b)) => {
+            Constant::String(format!("{}{}", a, b))
+        }
+        ("__eqeq", _, _) => Constant::Bool(left == right),
+        ("__noteq", _, _) => Constant::Bool(left != right),
+        ("__and", Constant::Bool(a), Constant::Bool(b)) => Constant::Bool(*a && *b),
+        ("__or", Constant::Bool(a), Constant::Bool(b)) => Constant::Bool(*a || *b),
+        (_, Constant::I64(a), Constant::I64(b)) => {
+            let result = match operator {
+                "__plus" => a.checked_add(*b),
+                "__minus" => a.checked_sub(*b),
+                "__mult" => a.checked_mul(*b),
+                "__div" => return Ok(Constant::F64(*a as f64 / *b as f64)),
+                "__mod" => a.checked_rem(*b),
+                "__lt" => return Ok(Constant::Bool(a < b)),
+                "__lte" => return Ok(Constant::Bool(a <= b)),
+                "__gt" => return Ok(Constant::Bool(a > b)),
+                "__gte" => return Ok(Constant::Bool(a >= b)),
+                _ => return mismatch(&left, &right),
+            };
+
+            match result {
+                Some(result) => Constant::I64(result),
+                None => return Err("integer overflow".to_string()),
+            }
+        }
+        (_, Constant::I64(_) | Constant::F64(_), Constant::I64(_) | Constant::F64(_)) => {
+            let (a, b) = (as_float(&left), as_float(&right));
+
+            match operator {
+                "__plus" => Constant::F64(a + b),
+                "__minus" => Constant::F64(a - b),
+                "__mult" => Constant::F64(a * b),
+                "__div" => Constant::F64(a / b),
+                "__mod" => Constant::F64(a % b),
+                "__lt" => Constant::Bool(a < b),
+                "__lte" => Constant::Bool(a <= b),
+                "__gt" => Constant::Bool(a > b),
+                "__gte" => Constant::Bool(a >= b),
+                _ => return mismatch(&left, &right),
+            }
+        }
+        _ => return mismatch(&left, &right),
+    };
+
+    Ok(value)
+}
+
+fn symbol(operator: &str) -> &str {
+    match operator {
+        "__plus" => "+",
+        "__minus" => "-",
+        "__mult" => "*",
+        "__div" => "/",
+        "__mod" => "%",
+        "__lt" => "<",
+        "__lte" => "<=",
+        "__gt" => ">",
+        "__gte" => ">=",
+        "__and" => "and",
+        "__or" => "or",
+        operator => operator,
+    }
+}
+
+fn as_float(constant: &Constant) -> f64 {
+    match constant {
+        Constant::I64(i) => *i as f64,
+        Constant::F64(f) => *f,
+        _ => unreachable!(),
+    }
+}
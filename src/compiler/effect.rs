@@ -0,0 +1,99 @@
+use std::fmt::Display;
+
+/// The number of values a piece of code pops from the stack it is given and
+/// the number of values it leaves in their place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StackEffect {
+    pub inputs: usize,
+    pub outputs: usize,
+    /// Whether the code never returns, like `throw`, so that its outputs are
+    /// meaningless.
+    pub diverges: bool,
+}
+
+impl StackEffect {
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        Self {
+            inputs,
+            outputs,
+            diverges: false,
+        }
+    }
+
+    /// The effect of code that pops `inputs` values and never returns.
+    pub fn diverging(inputs: usize) -> Self {
+        Self {
+            inputs,
+            outputs: 0,
+            diverges: true,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(0, 0)
+    }
+
+    /// How much deeper the stack is after running the code.
+    pub fn net(&self) -> isize {
+        self.outputs as isize - self.inputs as isize
+    }
+
+    /// The effect of running `self` followed by `other`.
+    pub fn then(&self, other: &Self) -> Self {
+        // Nothing after code that never returns runs.
+        if self.diverges {
+            return *self;
+        }
+
+        let effect = if self.outputs >= other.inputs {
+            Self::new(self.inputs, self.outputs - other.inputs + other.outputs)
+        } else {
+            Self::new(self.inputs + other.inputs - self.outputs, other.outputs)
+        };
+
+        Self {
+            diverges: other.diverges,
+            ..effect
+        }
+    }
+
+    /// Whether `self` and `other` leave the stack at the same depth, as the
+    /// branches of a conditional must. Code that never returns fits any depth.
+    pub fn joins(&self, other: &Self) -> bool {
+        self.diverges || other.diverges || self.net() == other.net()
+    }
+
+    /// The effect of running either `self` or `other`, which must join.
+    pub fn join(&self, other: &Self) -> Self {
+        let inputs = self.inputs.max(other.inputs);
+
+        match (self.diverges, other.diverges) {
+            (true, true) => Self::diverging(inputs),
+            (true, false) => other.widen(inputs),
+            _ => self.widen(inputs),
+        }
+    }
+
+    /// Raises the number of inputs to `inputs`, passing the extra values through untouched.
+    pub fn widen(&self, inputs: usize) -> Self {
+        if inputs <= self.inputs {
+            *self
+        } else {
+            Self {
+                inputs,
+                outputs: self.outputs + inputs - self.inputs,
+                diverges: self.diverges,
+            }
+        }
+    }
+}
+
+impl Display for StackEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.diverges {
+            write!(f, "({} -> never)", self.inputs)
+        } else {
+            write!(f, "({} -> {})", self.inputs, self.outputs)
+        }
+    }
+}
@@ -1,25 +1,334 @@
 use crate::token::location::Location;
 
+use super::effect::StackEffect;
+
 #[derive(Debug, Clone)]
 pub enum CompilerError {
     UnknownFunction(String),
+    UnknownVariant { name: String, location: Location },
+    EmptyMatch(Location),
+}
+
+impl std::fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompilerError::UnknownFunction(name) => write!(f, "Unknown function `{}`", name),
+            CompilerError::UnknownVariant { name, location } => {
+                write!(f, "Unknown variant `{}` at {}", name, location)
+            }
+            CompilerError::EmptyMatch(location) => {
+                write!(f, "Match has no cases at {}", location)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ResolverError {
     CallToMain(Location),
+    MissingMain(Option<Location>),
+    DuplicateFunction {
+        name: String,
+        /// Where the function was first declared.
+        previous: Location,
+        location: Location,
+    },
+    UndefinedFunction {
+        name: String,
+        location: Location,
+    },
+    ShadowedNative {
+        name: String,
+        location: Location,
+    },
+    UnknownIdentifier {
+        name: String,
+        location: Location,
+    },
+    MisplacedFunction {
+        name: String,
+        location: Location,
+    },
+    MisplacedGlobal {
+        name: String,
+        location: Location,
+    },
+    MisplacedType {
+        name: String,
+        location: Location,
+    },
+    DuplicateGlobal {
+        name: String,
+        location: Location,
+    },
+    UnknownGlobal {
+        name: String,
+        location: Location,
+    },
+    BareGlobal {
+        name: String,
+        location: Location,
+    },
+    ShadowedGlobal {
+        name: String,
+        location: Location,
+    },
+    InvalidConstant {
+        name: String,
+        reason: String,
+        location: Location,
+    },
+    InvalidStep(Location),
+    TryConsumesStack {
+        effect: StackEffect,
+        location: Location,
+    },
+    TryEffectMismatch {
+        body: StackEffect,
+        handler: StackEffect,
+        location: Location,
+    },
+    InvalidRangeBound(Location),
+    BranchEffectMismatch {
+        then_effect: StackEffect,
+        else_effect: StackEffect,
+        location: Location,
+    },
+    UnbalancedLoop {
+        effect: StackEffect,
+        location: Location,
+    },
+    SignatureMismatch {
+        name: String,
+        declared: StackEffect,
+        actual: StackEffect,
+        location: Location,
+    },
+    DuplicateField {
+        structure: String,
+        field: String,
+        location: Location,
+    },
+    UnknownField {
+        field: String,
+        location: Location,
+    },
+    DuplicateStruct {
+        name: String,
+        location: Location,
+    },
+    DuplicateEnum {
+        name: String,
+        location: Location,
+    },
+    DuplicateVariant {
+        name: String,
+        location: Location,
+    },
+    UnknownVariant {
+        name: String,
+        location: Location,
+    },
+    DuplicateCase {
+        name: String,
+        location: Location,
+    },
+    EmptyMatch(Location),
+    MixedMatch {
+        enumeration: String,
+        variant: String,
+        location: Location,
+    },
+    CaseEffectMismatch {
+        first: StackEffect,
+        other: StackEffect,
+        location: Location,
+    },
 }
 
 impl std::fmt::Display for ResolverError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ResolverError::CallToMain(location) => {
-                write!(
-                    f,
-                    "Unexpected call to main at {}:{}",
-                    location.line, location.column
-                )
+                write!(f, "Unexpected call to main at {}", location)
+            }
+            ResolverError::MissingMain(Some(location)) => {
+                write!(f, "No `main` function is declared in {}", location.file)
             }
+            ResolverError::MissingMain(None) => write!(f, "No `main` function is declared"),
+            ResolverError::DuplicateFunction {
+                name,
+                previous,
+                location,
+            } => write!(
+                f,
+                "Function `{}` is declared again at {}, it was first declared at {}",
+                name, location, previous
+            ),
+            ResolverError::ShadowedNative { name, location } => write!(
+                f,
+                "Function `{}` has the name of a native word at {}",
+                name, location
+            ),
+            ResolverError::UndefinedFunction { name, location } => {
+                write!(f, "Call to undefined function `{}` at {}", name, location)
+            }
+            ResolverError::UnknownIdentifier { name, location } => {
+                write!(f, "Unknown identifier `{}` at {}", name, location)
+            }
+            ResolverError::MisplacedFunction { name, location } => write!(
+                f,
+                "Function `{}` must be declared at the top level or directly in a function body at {}",
+                name, location
+            ),
+            ResolverError::BranchEffectMismatch {
+                then_effect,
+                else_effect,
+                location,
+            } => write!(
+                f,
+                "Branches of if leave the stack at different depths (then {:+}, else {:+}) at {}",
+                then_effect.net(),
+                else_effect.net(),
+                location
+            ),
+            ResolverError::UnbalancedLoop { effect, location } => write!(
+                f,
+                "Loop body changes the stack depth by {:+} on every iteration at {}",
+                effect.net(),
+                location
+            ),
+            ResolverError::SignatureMismatch {
+                name,
+                declared,
+                actual,
+                location,
+            } => write!(
+                f,
+                "Function `{}` declares stack effect {} but its body has effect {} at {}",
+                name, declared, actual, location
+            ),
+            ResolverError::MisplacedGlobal { name, location } => write!(
+                f,
+                "`{}` must be declared at the top level at {}",
+                name, location
+            ),
+            ResolverError::MisplacedType { name, location } => write!(
+                f,
+                "Type `{}` must be declared at the top level at {}",
+                name, location
+            ),
+            ResolverError::DuplicateGlobal { name, location } => {
+                write!(f, "`{}` is already declared at {}", name, location)
+            }
+            ResolverError::UnknownGlobal { name, location } => {
+                write!(f, "Unknown global variable `{}` at {}", name, location)
+            }
+            ResolverError::BareGlobal { name, location } => write!(
+                f,
+                "Global variable `{}` must be used as `{} @` or `{} !` at {}",
+                name, name, name, location
+            ),
+            ResolverError::ShadowedGlobal { name, location } => write!(
+                f,
+                "Local `{}` shadows the global variable `{}` at {}",
+                name, name, location
+            ),
+            ResolverError::InvalidConstant {
+                name,
+                reason,
+                location,
+            } => write!(
+                f,
+                "Invalid constant `{}`: {} at {}",
+                name, reason, location
+            ),
+            ResolverError::InvalidStep(location) => write!(
+                f,
+                "The step of a for loop must be a non-zero integer literal or constant at {}",
+                location
+            ),
+            ResolverError::TryConsumesStack { effect, location } => write!(
+                f,
+                "The body of try takes {} value(s) pushed before it, which would be lost on error at {}",
+                effect.inputs, location
+            ),
+            ResolverError::TryEffectMismatch {
+                body,
+                handler,
+                location,
+            } => write!(
+                f,
+                "Body of try and its handler leave the stack at different depths (try {:+}, catch {:+}) at {}",
+                body.net(),
+                handler.net(),
+                location
+            ),
+            ResolverError::InvalidRangeBound(location) => write!(
+                f,
+                "A bound of a for loop must push exactly one value at {}",
+                location
+            ),
+            ResolverError::DuplicateField {
+                structure,
+                field,
+                location,
+            } => write!(
+                f,
+                "Field `{}` is declared more than once in struct `{}` at {}",
+                field, structure, location
+            ),
+            ResolverError::UnknownField { field, location } => {
+                write!(f, "No struct has a field named `{}` at {}", field, location)
+            }
+            ResolverError::DuplicateStruct { name, location } => write!(
+                f,
+                "Struct `{}` reuses the name of another type, variant or function at {}",
+                name, location
+            ),
+            ResolverError::DuplicateEnum { name, location } => write!(
+                f,
+                "Enum `{}` reuses the name of another type at {}",
+                name, location
+            ),
+            ResolverError::DuplicateVariant { name, location } => {
+                write!(f, "Variant `{}` is already declared at {}", name, location)
+            }
+            ResolverError::UnknownVariant { name, location } => {
+                write!(f, "No enum has a variant named `{}` at {}", name, location)
+            }
+            ResolverError::DuplicateCase { name, location } => write!(
+                f,
+                "Variant `{}` is matched more than once at {}",
+                name, location
+            ),
+            ResolverError::EmptyMatch(location) => {
+                write!(f, "Match must have at least one case at {}", location)
+            }
+            ResolverError::MixedMatch {
+                enumeration,
+                variant,
+                location,
+            } => write!(
+                f,
+                "Variant `{}` does not belong to enum `{}` at {}",
+                variant, enumeration, location
+            ),
+            ResolverError::CaseEffectMismatch {
+                first,
+                other,
+                location,
+            } => write!(
+                f,
+                "Cases of match leave the stack at different depths ({:+} and {:+}) at {}",
+                first.net(),
+                other.net(),
+                location
+            ),
         }
     }
 }
+
+impl std::error::Error for CompilerError {}
+
+impl std::error::Error for ResolverError {}
@@ -1,6 +1,9 @@
 use crate::token::location::Location;
 
+use super::effect::StackEffect;
+
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub enum InstructionKind {
     NoOp,
     Patch,
@@ -12,6 +15,40 @@ pub enum InstructionKind {
 
     Get(String),
     Call(usize),
+    /// Calls the native word registered at `index` in `Program::natives`.
+    CallNative(usize),
+
+    LoadGlobal(usize),
+    StoreGlobal(usize),
+
+    Bind(usize),
+    Unbind(usize),
+    GetLocal(usize),
+    SetLocal(usize),
+    MakeClosure {
+        block: usize,
+        captures: Vec<usize>,
+    },
+    CallClosure,
+
+    MakeRecord {
+        type_id: usize,
+        fields: usize,
+    },
+    GetField(String),
+    SetField(String),
+
+    MakeVariant {
+        type_id: usize,
+        tag: usize,
+        fields: usize,
+    },
+    /// Pops a variant of the enum `type_id`, pushes its fields and jumps to the
+    /// entry of `table` for its tag.
+    Match {
+        type_id: usize,
+        table: Vec<Option<usize>>,
+    },
 
     Add,
     Sub,
@@ -34,17 +71,230 @@ pub enum InstructionKind {
     Swap,
     Over,
     Print,
+    /// Pushes the next line of input without its line ending, or `false` at
+    /// the end of the input.
+    ReadLine,
+    /// Pushes the rest of the input as a single string.
+    ReadAll,
+    /// Reads the next line of input as an integer, or pushes `false` at the
+    /// end of the input.
+    ReadInt,
+
+    /// Pops a path and pushes the contents of the file.
+    FileRead,
+    /// Pops a path and a string, and writes the string to the file.
+    FileWrite,
+    /// Pops a path and a string, and appends the string to the file.
+    FileAppend,
+    FileExists,
+    /// Pops a path and pushes the lines of the file as a list of strings.
+    FileLines,
+    /// Pops a path and pushes the sorted names of the entries of the directory.
+    DirList,
+
+    /// Pushes the command-line arguments given to the program as a list of strings.
+    Args,
+    /// Pops the name of an environment variable and pushes its value, or
+    /// `false` when it isn't set.
+    EnvGet,
+
+    ListNew,
+    ListPush,
+    ListGet,
+    ListSet,
+    Length,
+    ToString,
+    Panic,
 
     Jump(usize),
     JumpIfFalse(usize),
     JumpIfTrue(usize),
 
+    /// Installs a handler at the given instruction of the current block,
+    /// active until the matching `PopHandler`.
+    PushHandler(usize),
+    PopHandler,
+    Throw,
+
     DebugStack,
 
     Return,
     Halt,
 }
 
+impl InstructionKind {
+    /// The stack effect of the instruction, or `None` when it depends on the
+    /// code being called.
+    pub fn stack_effect(&self) -> Option<StackEffect> {
+        let (inputs, outputs) = match self {
+            InstructionKind::Panic | InstructionKind::Throw => {
+                return Some(StackEffect::diverging(1))
+            }
+
+            InstructionKind::NoOp
+            | InstructionKind::Patch
+            | InstructionKind::Unbind(_)
+            | InstructionKind::Jump(_)
+            | InstructionKind::PushHandler(_)
+            | InstructionKind::PopHandler
+            | InstructionKind::DebugStack
+            | InstructionKind::Return
+            | InstructionKind::Halt => (0, 0),
+
+            InstructionKind::LoadI64(_)
+            | InstructionKind::LoadF64(_)
+            | InstructionKind::LoadBool(_)
+            | InstructionKind::LoadConstant(_)
+            | InstructionKind::Get(_)
+            | InstructionKind::GetLocal(_)
+            | InstructionKind::LoadGlobal(_)
+            | InstructionKind::MakeClosure { .. }
+            | InstructionKind::ReadLine
+            | InstructionKind::ReadAll
+            | InstructionKind::ReadInt
+            | InstructionKind::Args => (0, 1),
+
+            InstructionKind::Add
+            | InstructionKind::Sub
+            | InstructionKind::Mul
+            | InstructionKind::Div
+            | InstructionKind::Mod
+            | InstructionKind::Pow
+            | InstructionKind::And
+            | InstructionKind::Or
+            | InstructionKind::Equals
+            | InstructionKind::NotEquals
+            | InstructionKind::LessThan
+            | InstructionKind::LessThanEquals
+            | InstructionKind::GreaterThan
+            | InstructionKind::GreaterThanEquals => (2, 1),
+
+            InstructionKind::Neg
+            | InstructionKind::Not
+            | InstructionKind::GetField(_)
+            | InstructionKind::Length
+            | InstructionKind::ToString
+            | InstructionKind::FileRead
+            | InstructionKind::FileExists
+            | InstructionKind::FileLines
+            | InstructionKind::DirList
+            | InstructionKind::EnvGet => (1, 1),
+            InstructionKind::FileWrite | InstructionKind::FileAppend => (2, 0),
+            InstructionKind::SetField(_) | InstructionKind::ListPush | InstructionKind::ListGet => {
+                (2, 1)
+            }
+            InstructionKind::ListSet => (3, 1),
+            InstructionKind::ListNew => (0, 1),
+            InstructionKind::MakeRecord { fields, .. }
+            | InstructionKind::MakeVariant { fields, .. } => (*fields, 1),
+            InstructionKind::Dup => (1, 2),
+            InstructionKind::Drop | InstructionKind::Print | InstructionKind::StoreGlobal(_) => {
+                (1, 0)
+            }
+            InstructionKind::Swap => (2, 2),
+            InstructionKind::Over => (2, 3),
+            InstructionKind::Bind(count) => (*count, 0),
+            InstructionKind::SetLocal(_) => (1, 0),
+            InstructionKind::JumpIfFalse(_) | InstructionKind::JumpIfTrue(_) => (1, 0),
+
+            // The values a match pushes depend on the variant it jumps to.
+            InstructionKind::Call(_)
+            | InstructionKind::CallNative(_)
+            | InstructionKind::CallClosure
+            | InstructionKind::Match { .. } => return None,
+        };
+
+        Some(StackEffect::new(inputs, outputs))
+    }
+}
+
+impl std::fmt::Display for InstructionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstructionKind::NoOp => write!(f, "noop"),
+            InstructionKind::Patch => write!(f, "patch"),
+            InstructionKind::LoadI64(value) => write!(f, "load_i64 {}", value),
+            InstructionKind::LoadF64(value) => write!(f, "load_f64 {}", value),
+            InstructionKind::LoadBool(value) => write!(f, "load_bool {}", value),
+            InstructionKind::LoadConstant(index) => write!(f, "load_constant {}", index),
+            InstructionKind::Get(name) => write!(f, "get {}", name),
+            InstructionKind::Call(block) => write!(f, "call {}", block),
+            InstructionKind::CallNative(index) => write!(f, "call_native {}", index),
+            InstructionKind::LoadGlobal(index) => write!(f, "load_global {}", index),
+            InstructionKind::StoreGlobal(index) => write!(f, "store_global {}", index),
+            InstructionKind::Bind(count) => write!(f, "bind {}", count),
+            InstructionKind::Unbind(count) => write!(f, "unbind {}", count),
+            InstructionKind::GetLocal(slot) => write!(f, "get_local {}", slot),
+            InstructionKind::SetLocal(slot) => write!(f, "set_local {}", slot),
+            InstructionKind::MakeClosure { block, captures } => {
+                write!(f, "make_closure {} {:?}", block, captures)
+            }
+            InstructionKind::CallClosure => write!(f, "call_closure"),
+            InstructionKind::MakeRecord { type_id, fields } => {
+                write!(f, "make_record {} {}", type_id, fields)
+            }
+            InstructionKind::GetField(field) => write!(f, "get_field {}", field),
+            InstructionKind::SetField(field) => write!(f, "set_field {}", field),
+            InstructionKind::MakeVariant {
+                type_id,
+                tag,
+                fields,
+            } => write!(f, "make_variant {} {} {}", type_id, tag, fields),
+            InstructionKind::Match { type_id, table } => {
+                write!(f, "match {} {:?}", type_id, table)
+            }
+            InstructionKind::Add => write!(f, "add"),
+            InstructionKind::Sub => write!(f, "sub"),
+            InstructionKind::Mul => write!(f, "mul"),
+            InstructionKind::Div => write!(f, "div"),
+            InstructionKind::Mod => write!(f, "mod"),
+            InstructionKind::Pow => write!(f, "pow"),
+            InstructionKind::Neg => write!(f, "neg"),
+            InstructionKind::Not => write!(f, "not"),
+            InstructionKind::And => write!(f, "and"),
+            InstructionKind::Or => write!(f, "or"),
+            InstructionKind::Equals => write!(f, "equals"),
+            InstructionKind::NotEquals => write!(f, "not_equals"),
+            InstructionKind::LessThan => write!(f, "less_than"),
+            InstructionKind::LessThanEquals => write!(f, "less_than_equals"),
+            InstructionKind::GreaterThan => write!(f, "greater_than"),
+            InstructionKind::GreaterThanEquals => write!(f, "greater_than_equals"),
+            InstructionKind::Dup => write!(f, "dup"),
+            InstructionKind::Drop => write!(f, "drop"),
+            InstructionKind::Swap => write!(f, "swap"),
+            InstructionKind::Over => write!(f, "over"),
+            InstructionKind::Print => write!(f, "print"),
+            InstructionKind::ReadLine => write!(f, "read_line"),
+            InstructionKind::ReadAll => write!(f, "read_all"),
+            InstructionKind::ReadInt => write!(f, "read_int"),
+            InstructionKind::FileRead => write!(f, "file_read"),
+            InstructionKind::FileWrite => write!(f, "file_write"),
+            InstructionKind::FileAppend => write!(f, "file_append"),
+            InstructionKind::FileExists => write!(f, "file_exists"),
+            InstructionKind::FileLines => write!(f, "file_lines"),
+            InstructionKind::DirList => write!(f, "dir_list"),
+            InstructionKind::Args => write!(f, "args"),
+            InstructionKind::EnvGet => write!(f, "env_get"),
+            InstructionKind::ListNew => write!(f, "list_new"),
+            InstructionKind::ListPush => write!(f, "list_push"),
+            InstructionKind::ListGet => write!(f, "list_get"),
+            InstructionKind::ListSet => write!(f, "list_set"),
+            InstructionKind::Length => write!(f, "length"),
+            InstructionKind::ToString => write!(f, "to_string"),
+            InstructionKind::Panic => write!(f, "panic"),
+            InstructionKind::Jump(target) => write!(f, "jump {}", target),
+            InstructionKind::JumpIfFalse(target) => write!(f, "jump_if_false {}", target),
+            InstructionKind::JumpIfTrue(target) => write!(f, "jump_if_true {}", target),
+            InstructionKind::PushHandler(target) => write!(f, "push_handler {}", target),
+            InstructionKind::PopHandler => write!(f, "pop_handler"),
+            InstructionKind::Throw => write!(f, "throw"),
+            InstructionKind::DebugStack => write!(f, "debug_stack"),
+            InstructionKind::Return => write!(f, "return"),
+            InstructionKind::Halt => write!(f, "halt"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Instruction {
     pub kind: InstructionKind,
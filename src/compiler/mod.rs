@@ -2,22 +2,32 @@ use crate::parser::ast::AstNode;
 
 use self::{
     block::Block,
+    builtins::builtin_instruction,
+    constant::Constant,
     error::CompilerError,
     instruction::{Instruction, InstructionKind},
-    program::Program,
+    program::{scoped_name, Program},
 };
 
 pub mod block;
+pub mod builtins;
+pub mod constant;
+pub mod effect;
 pub mod instruction;
 pub mod program;
 pub mod resolver;
 
-mod error;
+pub mod error;
 
 #[derive(Debug, Clone)]
 pub struct Compiler {
     program: Program,
     current_block: usize,
+
+    /// Key of the function being compiled, empty at the top level.
+    scope: String,
+    /// Names of the locals visible in the block being compiled, indexed by slot.
+    locals: Vec<String>,
 }
 
 impl Compiler {
@@ -25,6 +35,8 @@ impl Compiler {
         Compiler {
             program: resolved_program,
             current_block: 0,
+            scope: String::new(),
+            locals: Vec::new(),
         }
     }
 
@@ -32,6 +44,126 @@ impl Compiler {
         &mut self.program.blocks[self.current_block]
     }
 
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|local| local == name)
+    }
+
+    /// Collects, in order of first use, the enclosing locals referenced by `nodes`
+    /// that are not shadowed by a binding introduced inside them.
+    fn collect_captures(
+        &self,
+        nodes: &[AstNode],
+        bound: &mut Vec<String>,
+        captures: &mut Vec<String>,
+    ) {
+        for node in nodes {
+            match node {
+                // `x !` negates `x` when `x` is a local rather than a global.
+                AstNode::Identifier(name, _) | AstNode::GlobalStore { name, .. }
+                    if !bound.contains(name)
+                        && !captures.contains(name)
+                        && self.resolve_local(name).is_some() =>
+                {
+                    captures.push(name.clone());
+                }
+                AstNode::LetDeclaration { bindings, body, .. } => {
+                    let depth = bound.len();
+                    bound.extend(bindings.iter().cloned());
+
+                    self.collect_captures(&body.nodes, bound, captures);
+                    bound.truncate(depth);
+                }
+                AstNode::ForExpression {
+                    variable,
+                    start,
+                    end,
+                    body,
+                    ..
+                } => {
+                    self.collect_captures(std::slice::from_ref(start), bound, captures);
+                    self.collect_captures(std::slice::from_ref(end), bound, captures);
+
+                    bound.push(variable.clone());
+                    self.collect_captures(&body.nodes, bound, captures);
+                    bound.pop();
+                }
+                AstNode::Quotation { body, .. } => {
+                    self.collect_captures(&body.nodes, bound, captures)
+                }
+                AstNode::IfExpression {
+                    then_branch,
+                    else_branch,
+                    ..
+                } => {
+                    self.collect_captures(&then_branch.nodes, bound, captures);
+
+                    if let Some(else_branch) = else_branch {
+                        self.collect_captures(&else_branch.nodes, bound, captures);
+                    }
+                }
+                AstNode::WhileExpression {
+                    condition, body, ..
+                } => {
+                    self.collect_captures(&condition.nodes, bound, captures);
+                    self.collect_captures(&body.nodes, bound, captures);
+                }
+                AstNode::MatchExpression { cases, .. } => {
+                    for case in cases {
+                        self.collect_captures(&case.body.nodes, bound, captures);
+                    }
+                }
+                AstNode::TryExpression { body, handler, .. } => {
+                    self.collect_captures(&body.nodes, bound, captures);
+                    self.collect_captures(&handler.nodes, bound, captures);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn compile_word(&mut self, name: &str, node: &AstNode) -> Result<(), CompilerError> {
+        let kind = match builtin_instruction(name) {
+            Some(kind) => kind,
+            None => match self.program.lookup_function(name, &self.scope) {
+                Some(index) => InstructionKind::Call(index),
+                None => match self.program.natives.index(name) {
+                    Some(index) => InstructionKind::CallNative(index),
+                    None => match self.program.struct_index(name) {
+                        Some(type_id) => InstructionKind::MakeRecord {
+                            type_id,
+                            fields: self.program.structs[type_id].fields.len(),
+                        },
+                        None => match self.program.variant_index(name) {
+                            Some((type_id, tag)) => InstructionKind::MakeVariant {
+                                type_id,
+                                tag,
+                                fields: self.program.enums[type_id].variants[tag].fields,
+                            },
+                            None => return Err(CompilerError::UnknownFunction(name.to_string())),
+                        },
+                    },
+                },
+            },
+        };
+
+        self.current_block()
+            .add_instruction(Instruction::new(kind, node.location()));
+
+        Ok(())
+    }
+
+    fn compile_constant(&mut self, value: Constant, node: &AstNode) {
+        let kind = match value {
+            Constant::I64(i) => InstructionKind::LoadI64(i),
+            Constant::F64(f) => InstructionKind::LoadF64(f),
+            Constant::Bool(b) => InstructionKind::LoadBool(b),
+            Constant::String(s) => InstructionKind::LoadConstant(self.program.add_string(&s)),
+        };
+
+        self.current_block()
+            .add_instruction(Instruction::new(kind, node.location()));
+    }
+
     fn compile_node(&mut self, node: &AstNode) -> Result<(), CompilerError> {
         match node {
             AstNode::IntegerLiteral(i, _) => self.program.add_instruction_at(
@@ -54,93 +186,25 @@ impl Compiler {
                     Instruction::new(InstructionKind::LoadConstant(index), node.location()),
                 );
             }
-            AstNode::FunctionCall { name, .. } => match name.as_str() {
-                "__plus" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Add, node.location()),
-                ),
-                "__minus" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Sub, node.location()),
-                ),
-                "__mult" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Mul, node.location()),
-                ),
-                "__div" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Div, node.location()),
-                ),
-                "__mod" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Mod, node.location()),
-                ),
-                "__not" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Not, node.location()),
-                ),
-                "__gt" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::GreaterThan, node.location()),
-                ),
-                "__gte" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::GreaterThanEquals, node.location()),
-                ),
-                "__lt" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::LessThan, node.location()),
-                ),
-                "__lte" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::LessThanEquals, node.location()),
-                ),
-                "__and" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::And, node.location()),
-                ),
-                "__or" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Or, node.location()),
-                ),
-                "__eqeq" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Equals, node.location()),
-                ),
-                "__noteq" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::NotEquals, node.location()),
-                ),
-                "()__quit" => self.program.add_instruction_at(
-                    self.current_block,
-                    Instruction::new(InstructionKind::Halt, node.location()),
-                ),
-                _ => {
-                    let index = match self.program.functions.get(name) {
-                        Some(i) => *i,
-                        None => return Err(CompilerError::UnknownFunction(name.clone())),
-                    };
-
-                    self.program.add_instruction_at(
-                        self.current_block,
-                        Instruction::new(InstructionKind::Call(index), node.location()),
-                    );
-                }
-            },
+            AstNode::FunctionCall { name, .. } => self.compile_word(name, node)?,
             AstNode::FunctionDeclaration { name, body, .. } => {
-                let entry_point = match self.program.functions.get(name) {
+                let key = scoped_name(&self.scope, name);
+                let entry_point = match self.program.functions.get(&key) {
                     Some(index) => *index,
                     None => unreachable!(),
                 };
 
+                // Nested functions don't capture the locals around their declaration.
                 let old_block = self.current_block;
+                let old_scope = std::mem::replace(&mut self.scope, key);
+                let old_locals = std::mem::take(&mut self.locals);
                 self.current_block = entry_point;
 
                 for node in &body.nodes {
                     self.compile_node(node)?;
                 }
 
-                let instruction = if name == "main" {
+                let instruction = if self.scope == "main" {
                     Instruction::new(InstructionKind::Halt, node.location())
                 } else {
                     Instruction::new(InstructionKind::Return, node.location())
@@ -150,6 +214,134 @@ impl Compiler {
                     .add_instruction_at(self.current_block, instruction);
 
                 self.current_block = old_block;
+                self.scope = old_scope;
+                self.locals = old_locals;
+            }
+            AstNode::LetDeclaration { bindings, body, .. } => {
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::Bind(bindings.len()),
+                    node.location(),
+                ));
+
+                let depth = self.locals.len();
+                self.locals.extend(bindings.iter().cloned());
+
+                for node in &body.nodes {
+                    self.compile_node(node)?;
+                }
+
+                self.locals.truncate(depth);
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::Unbind(bindings.len()),
+                    node.location(),
+                ));
+            }
+            AstNode::Quotation { body, .. } => {
+                let mut captures = Vec::new();
+                self.collect_captures(&body.nodes, &mut Vec::new(), &mut captures);
+
+                let slots = captures
+                    .iter()
+                    .filter_map(|name| self.resolve_local(name))
+                    .collect();
+
+                let old_block = self.current_block;
+                let old_locals = std::mem::replace(&mut self.locals, captures);
+                self.current_block = self.program.add_block();
+
+                for node in &body.nodes {
+                    self.compile_node(node)?;
+                }
+
+                self.current_block()
+                    .add_instruction(Instruction::new(InstructionKind::Return, node.location()));
+
+                let block = self.current_block;
+                self.current_block = old_block;
+                self.locals = old_locals;
+
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::MakeClosure {
+                        block,
+                        captures: slots,
+                    },
+                    node.location(),
+                ));
+            }
+            AstNode::ForExpression {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                let step = match step {
+                    Some(step) => match self.program.constant_integer(step) {
+                        Some(step) => step,
+                        None => unreachable!(),
+                    },
+                    None => 1,
+                };
+
+                // The bounds are evaluated in source order. The end of the range
+                // is kept in a local the program can't name, right above the
+                // loop variable.
+                self.compile_node(start)?;
+                self.compile_node(end)?;
+                self.current_block()
+                    .add_instruction(Instruction::new(InstructionKind::Bind(2), node.location()));
+
+                self.locals.push(variable.clone());
+                self.locals.push("(end)".to_string());
+                let index = self.locals.len() - 2;
+
+                let condition_entry = self.current_block().instructions.len();
+                let comparison = if step > 0 {
+                    InstructionKind::LessThan
+                } else {
+                    InstructionKind::GreaterThan
+                };
+
+                for kind in [
+                    InstructionKind::GetLocal(index),
+                    InstructionKind::GetLocal(index + 1),
+                    comparison,
+                ] {
+                    self.current_block()
+                        .add_instruction(Instruction::new(kind, node.location()));
+                }
+
+                let instruction_to_patch = self
+                    .current_block()
+                    .add_instruction(Instruction::new(InstructionKind::Patch, node.location()));
+
+                for node in &body.nodes {
+                    self.compile_node(node)?;
+                }
+
+                for kind in [
+                    InstructionKind::GetLocal(index),
+                    InstructionKind::LoadI64(step),
+                    InstructionKind::Add,
+                    InstructionKind::SetLocal(index),
+                    InstructionKind::Jump(condition_entry),
+                ] {
+                    self.current_block()
+                        .add_instruction(Instruction::new(kind, node.location()));
+                }
+
+                let exit = self.current_block().instructions.len();
+                self.current_block().patch_instruction(
+                    instruction_to_patch,
+                    Instruction::new(InstructionKind::JumpIfFalse(exit), node.location()),
+                );
+
+                self.locals.truncate(index);
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::Unbind(2),
+                    node.location(),
+                ));
             }
             AstNode::IfExpression {
                 then_branch,
@@ -194,6 +386,40 @@ impl Compiler {
                     Instruction::new(InstructionKind::Jump(index), node.location()),
                 );
             }
+            AstNode::TryExpression { body, handler, .. } => {
+                let instruction_to_patch = self
+                    .current_block()
+                    .add_instruction(Instruction::new(InstructionKind::Patch, node.location()));
+
+                for node in &body.nodes {
+                    self.compile_node(node)?;
+                }
+
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::PopHandler,
+                    node.location(),
+                ));
+                let end_body_instruction = self
+                    .current_block()
+                    .add_instruction(Instruction::new(InstructionKind::Patch, node.location()));
+
+                let handler_entry = self.current_block().instructions.len();
+
+                for node in &handler.nodes {
+                    self.compile_node(node)?;
+                }
+
+                self.current_block().patch_instruction(
+                    instruction_to_patch,
+                    Instruction::new(InstructionKind::PushHandler(handler_entry), node.location()),
+                );
+
+                let index = self.current_block().instructions.len();
+                self.current_block().patch_instruction(
+                    end_body_instruction,
+                    Instruction::new(InstructionKind::Jump(index), node.location()),
+                );
+            }
             AstNode::WhileExpression {
                 condition, body, ..
             } => {
@@ -222,44 +448,129 @@ impl Compiler {
                     Instruction::new(InstructionKind::JumpIfFalse(index), node.location()),
                 );
             }
-            AstNode::Identifier(name, _) => {
-                match name.as_str() {
-                    "dup" => self
-                        .current_block()
-                        .add_instruction(Instruction::new(InstructionKind::Dup, node.location())),
-                    "drop" => self
-                        .current_block()
-                        .add_instruction(Instruction::new(InstructionKind::Drop, node.location())),
-                    "print" => self
-                        .current_block()
-                        .add_instruction(Instruction::new(InstructionKind::Print, node.location())),
-                    "swap" => self
-                        .current_block()
-                        .add_instruction(Instruction::new(InstructionKind::Swap, node.location())),
-                    "over" => self
-                        .current_block()
-                        .add_instruction(Instruction::new(InstructionKind::Over, node.location())),
-                    "???" => self.current_block().add_instruction(Instruction::new(
-                        InstructionKind::DebugStack,
-                        node.location(),
-                    )),
-                    _ => {
-                        if self.program.functions.contains_key(name) {
-                            let index = self.program.functions[name];
+            // Imports are resolved by the `Loader` before compilation, and
+            // constants are inlined where they are used.
+            AstNode::StructDeclaration { .. }
+            | AstNode::EnumDeclaration { .. }
+            | AstNode::ImportDeclaration { .. }
+            | AstNode::ConstDeclaration { .. }
+            | AstNode::VarDeclaration { .. } => {}
+            AstNode::MatchExpression { cases, .. } => {
+                let first = match cases.first() {
+                    Some(case) => case,
+                    None => return Err(CompilerError::EmptyMatch(node.location().clone())),
+                };
 
-                            self.current_block().add_instruction(Instruction::new(
-                                InstructionKind::Call(index),
-                                node.location(),
-                            ));
+                let type_id = match self.program.variant_index(&first.variant) {
+                    Some((type_id, _)) => type_id,
+                    None => {
+                        return Err(CompilerError::UnknownVariant {
+                            name: first.variant.clone(),
+                            location: first.location.clone(),
+                        })
+                    }
+                };
 
-                            return Ok(());
+                let instruction_to_patch = self
+                    .current_block()
+                    .add_instruction(Instruction::new(InstructionKind::Patch, node.location()));
+
+                let mut table = vec![None; self.program.enums[type_id].variants.len()];
+                let mut exits = Vec::new();
+
+                for case in cases {
+                    let tag = match self.program.variant_index(&case.variant) {
+                        Some((_, tag)) => tag,
+                        None => {
+                            return Err(CompilerError::UnknownVariant {
+                                name: case.variant.clone(),
+                                location: case.location.clone(),
+                            })
                         }
+                    };
+
+                    table[tag] = Some(self.current_block().instructions.len());
+
+                    for node in &case.body.nodes {
+                        self.compile_node(node)?;
+                    }
+
+                    exits.push(
+                        self.current_block().add_instruction(Instruction::new(
+                            InstructionKind::Patch,
+                            &case.location,
+                        )),
+                    );
+                }
 
-                        todo!("compile_node::AstNode::Identifier not yet implemented for non function identifiers `{}`", name);
+                self.current_block().patch_instruction(
+                    instruction_to_patch,
+                    Instruction::new(InstructionKind::Match { type_id, table }, node.location()),
+                );
+
+                let index = self.current_block().instructions.len();
+                for exit in exits {
+                    let location = self.current_block().instructions[exit].location.clone();
+
+                    self.current_block().patch_instruction(
+                        exit,
+                        Instruction::new(InstructionKind::Jump(index), &location),
+                    );
+                }
+            }
+            AstNode::FieldGet { field, .. } => {
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::GetField(field.clone()),
+                    node.location(),
+                ));
+            }
+            AstNode::FieldSet { field, .. } => {
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::SetField(field.clone()),
+                    node.location(),
+                ));
+            }
+            AstNode::Identifier(name, _) => match self.resolve_local(name) {
+                Some(slot) => {
+                    self.current_block().add_instruction(Instruction::new(
+                        InstructionKind::GetLocal(slot),
+                        node.location(),
+                    ));
+                }
+                None => match self.program.constants.get(name) {
+                    Some(value) => self.compile_constant(value.clone(), node),
+                    None => self.compile_word(name, node)?,
+                },
+            },
+            AstNode::GlobalStore { name, location } => {
+                match self.program.global_index(name) {
+                    Some(index) => {
+                        self.current_block().add_instruction(Instruction::new(
+                            InstructionKind::StoreGlobal(index),
+                            node.location(),
+                        ));
+                    }
+                    // Not a global, so `!` is the negation operator.
+                    _ => {
+                        self.compile_node(&AstNode::Identifier(name.clone(), location.clone()))?;
+                        self.current_block().add_instruction(Instruction::new(
+                            InstructionKind::Not,
+                            node.location(),
+                        ));
                     }
+                }
+            }
+            AstNode::GlobalFetch { name, .. } => {
+                let index = match self.program.global_index(name) {
+                    Some(index) => index,
+                    None => unreachable!(),
                 };
+
+                self.current_block().add_instruction(Instruction::new(
+                    InstructionKind::LoadGlobal(index),
+                    node.location(),
+                ));
             }
-            _ => todo!("compile_node is not implemented for {:?} yet", node),
         }
 
         Ok(())
@@ -1,6 +1,44 @@
 use std::collections::HashMap;
 
-use super::{block::Block, instruction::Instruction};
+use crate::{parser::ast::AstNode, runtime::native::Natives};
+
+use super::{block::Block, constant::Constant, instruction::Instruction};
+
+/// The key under which a function declared inside `scope` is stored in
+/// `Program::functions`. Nested functions are keyed by the path of their
+/// enclosing functions (`outer/inner`), which can't clash with user names
+/// since `/` is not allowed in identifiers.
+pub fn scoped_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", scope, name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDefinition {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl StructDefinition {
+    pub fn field_index(&self, field: &str) -> Option<usize> {
+        self.fields.iter().position(|name| name == field)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantDefinition {
+    pub name: String,
+    pub fields: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDefinition {
+    pub name: String,
+    pub variants: Vec<VariantDefinition>,
+}
 
 #[derive(Debug, Clone)]
 pub struct Program {
@@ -9,17 +47,38 @@ pub struct Program {
 
     pub strings: Vec<String>,
     pub functions: HashMap<String, usize>,
+    pub structs: Vec<StructDefinition>,
+    pub enums: Vec<EnumDefinition>,
+    pub constants: HashMap<String, Constant>,
+    pub globals: Vec<String>,
+    pub natives: Natives,
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Program {
     pub fn new() -> Self {
-        Program {
+        let mut program = Program {
             blocks: Vec::new(),
             entry_point: 0,
 
             strings: Vec::new(),
             functions: HashMap::new(),
-        }
+            structs: Vec::new(),
+            enums: Vec::new(),
+            constants: HashMap::new(),
+            globals: Vec::new(),
+            natives: Natives::new(),
+        };
+
+        // Runtime errors are caught as records of this struct.
+        program.add_struct("Error", &["kind".to_string(), "message".to_string()]);
+
+        program
     }
 
     pub fn add_block(&mut self) -> usize {
@@ -48,4 +107,113 @@ impl Program {
     pub fn add_function(&mut self, name: &str, block_id: usize) {
         self.functions.insert(name.to_string(), block_id);
     }
+
+    /// How `block` is named in traces, profiles, backtraces and diagnostics:
+    /// after the function stored there, or as `closure@N` for a closure.
+    pub fn block_label(&self, block: usize) -> String {
+        match self.functions.iter().find(|(_, index)| **index == block) {
+            Some((name, _)) => name.clone(),
+            None => format!("closure@{}", block),
+        }
+    }
+
+    /// The label of every block, indexed by block.
+    pub fn block_labels(&self) -> Vec<String> {
+        (0..self.blocks.len())
+            .map(|block| self.block_label(block))
+            .collect()
+    }
+
+    /// Looks up a function as seen from inside the function `scope` (empty at
+    /// the top level): functions nested in `scope` or in any function enclosing
+    /// it take precedence over the top-level ones.
+    pub fn lookup_function(&self, name: &str, scope: &str) -> Option<usize> {
+        let mut scope = scope;
+
+        while !scope.is_empty() {
+            if let Some(index) = self.functions.get(&scoped_name(scope, name)) {
+                return Some(*index);
+            }
+
+            scope = scope.rsplit_once('/').map_or("", |(parent, _)| parent);
+        }
+
+        self.functions.get(name).copied()
+    }
+
+    pub fn add_constant(&mut self, name: &str, value: Constant) {
+        self.constants.insert(name.to_string(), value);
+    }
+
+    /// The value of a node that is an integer known at compile time: an
+    /// integer literal or the name of an integer constant.
+    pub fn constant_integer(&self, node: &AstNode) -> Option<i64> {
+        match node {
+            AstNode::IntegerLiteral(value, _) => Some(*value),
+            AstNode::Identifier(name, _) => match self.constants.get(name) {
+                Some(Constant::I64(value)) => Some(*value),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn add_global(&mut self, name: &str) -> usize {
+        self.globals.push(name.to_string());
+        self.globals.len() - 1
+    }
+
+    pub fn global_index(&self, name: &str) -> Option<usize> {
+        self.globals.iter().position(|global| global == name)
+    }
+
+    pub fn add_struct(&mut self, name: &str, fields: &[String]) -> usize {
+        self.structs.push(StructDefinition {
+            name: name.to_string(),
+            fields: fields.to_vec(),
+        });
+
+        self.structs.len() - 1
+    }
+
+    pub fn struct_index(&self, name: &str) -> Option<usize> {
+        self.structs
+            .iter()
+            .position(|definition| definition.name == name)
+    }
+
+    pub fn enum_index(&self, name: &str) -> Option<usize> {
+        self.enums
+            .iter()
+            .position(|definition| definition.name == name)
+    }
+
+    pub fn add_enum(&mut self, name: &str, variants: Vec<VariantDefinition>) -> usize {
+        self.enums.push(EnumDefinition {
+            name: name.to_string(),
+            variants,
+        });
+
+        self.enums.len() - 1
+    }
+
+    /// Finds the enum declaring the variant `name`, returning its type id and the variant's tag.
+    pub fn variant_index(&self, name: &str) -> Option<(usize, usize)> {
+        self.enums
+            .iter()
+            .enumerate()
+            .find_map(|(type_id, definition)| {
+                definition
+                    .variants
+                    .iter()
+                    .position(|variant| variant.name == name)
+                    .map(|tag| (type_id, tag))
+            })
+    }
+
+    pub fn has_field(&self, field: &str) -> bool {
+        self.structs
+            .iter()
+            .any(|definition| definition.field_index(field).is_some())
+    }
 }
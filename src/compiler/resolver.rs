@@ -1,11 +1,39 @@
-use crate::parser::ast::AstNode;
+use std::collections::{HashMap, HashSet};
 
-use super::{error::ResolverError, program::Program};
+use crate::{
+    analysis::diagnostic::Diagnostic,
+    parser::ast::{self, AstNode, MatchCase, Signature, VariantDeclaration},
+    token::location::Location,
+};
+
+use super::{
+    builtins::builtin_instruction,
+    constant,
+    effect::StackEffect,
+    error::ResolverError,
+    program::{scoped_name, Program, VariantDefinition},
+};
+use crate::runtime::native::Natives;
 
 #[derive(Debug)]
 pub struct Resolver {
     program: Program,
     current_block: usize,
+    warnings: Vec<Diagnostic>,
+    errors: Vec<ResolverError>,
+
+    /// Key of the function whose body is being resolved, empty at the top level.
+    scope: String,
+    /// Names bound by the `let` declarations enclosing the node being resolved.
+    locals: Vec<String>,
+    /// Where each function was declared, keyed as in `Program::functions`.
+    declarations: HashMap<String, Location>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Resolver {
@@ -13,37 +41,945 @@ impl Resolver {
         Self {
             program: Program::new(),
             current_block: 0,
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            scope: String::new(),
+            locals: Vec::new(),
+            declarations: HashMap::new(),
         }
     }
 
-    fn resolve_node(&mut self, node: &AstNode) -> Result<(), ResolverError> {
-        match node {
-            AstNode::FunctionDeclaration { name, body, .. } => {
-                self.current_block = self.program.add_block();
-                self.program.add_function(name, self.current_block);
+    /// A resolver for programs that can call the native words in `natives`.
+    pub fn with_natives(natives: Natives) -> Self {
+        let mut resolver = Self::new();
+        resolver.program.natives = natives;
 
-                for node in &body.nodes {
-                    self.resolve_node(node)?;
-                }
+        resolver
+    }
 
-                Ok(())
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        builtin_instruction(name).is_some()
+            || self.program.lookup_function(name, &self.scope).is_some()
+            || self.program.natives.index(name).is_some()
+            || self.program.struct_index(name).is_some()
+            || self.program.variant_index(name).is_some()
+    }
+
+    fn resolve_block(&mut self, block: &ast::Block) {
+        for node in &block.nodes {
+            self.resolve_node(node);
+        }
+    }
+
+    fn resolve_node(&mut self, node: &AstNode) {
+        match node {
+            // Functions declared directly in a body are handled by `resolve_body`.
+            AstNode::FunctionDeclaration { name, location, .. } => {
+                self.errors.push(ResolverError::MisplacedFunction {
+                    name: name.clone(),
+                    location: location.clone(),
+                })
             }
             AstNode::FunctionCall { name, location } => {
                 if name == "main" {
-                    return Err(ResolverError::CallToMain(location.clone()));
+                    self.errors
+                        .push(ResolverError::CallToMain(location.clone()));
+                } else if !self.is_defined(name) {
+                    self.errors.push(ResolverError::UndefinedFunction {
+                        name: name.clone(),
+                        location: location.clone(),
+                    });
+                }
+            }
+            AstNode::Identifier(name, location) => {
+                if self.locals.contains(name) || self.program.constants.contains_key(name) {
+                    return;
                 }
 
-                Ok(())
+                if name == "main" {
+                    self.errors
+                        .push(ResolverError::CallToMain(location.clone()));
+                } else if self.program.global_index(name).is_some() {
+                    self.errors.push(ResolverError::BareGlobal {
+                        name: name.clone(),
+                        location: location.clone(),
+                    });
+                } else if !self.is_defined(name) {
+                    self.errors.push(ResolverError::UnknownIdentifier {
+                        name: name.clone(),
+                        location: location.clone(),
+                    });
+                }
+            }
+            // `name !` negates `name` unless it names a global.
+            AstNode::GlobalStore { name, location }
+                if self.program.global_index(name).is_none() =>
+            {
+                self.resolve_node(&AstNode::Identifier(name.clone(), location.clone()));
+            }
+            AstNode::GlobalFetch { name, location }
+                if self.program.global_index(name).is_none() =>
+            {
+                self.errors.push(ResolverError::UnknownGlobal {
+                    name: name.clone(),
+                    location: location.clone(),
+                });
+            }
+            AstNode::ConstDeclaration { name, location, .. }
+            | AstNode::VarDeclaration { name, location } => {
+                self.errors.push(ResolverError::MisplacedGlobal {
+                    name: name.clone(),
+                    location: location.clone(),
+                })
+            }
+            AstNode::StructDeclaration { name, location, .. }
+            | AstNode::EnumDeclaration { name, location, .. } => {
+                self.errors.push(ResolverError::MisplacedType {
+                    name: name.clone(),
+                    location: location.clone(),
+                })
             }
-            _ => Ok(()),
+            AstNode::ForExpression {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                location,
+            } => {
+                self.check_shadowing(variable, location);
+                self.resolve_node(start);
+                self.resolve_node(end);
+
+                if let Some(step) = step {
+                    self.resolve_node(step);
+
+                    if !matches!(self.program.constant_integer(step), Some(step) if step != 0) {
+                        self.errors
+                            .push(ResolverError::InvalidStep(step.location().clone()));
+                    }
+                }
+
+                self.locals.push(variable.clone());
+                self.resolve_block(body);
+                self.locals.pop();
+            }
+            AstNode::LetDeclaration {
+                bindings,
+                body,
+                location,
+            } => {
+                for binding in bindings {
+                    self.check_shadowing(binding, location);
+                }
+
+                let depth = self.locals.len();
+                self.locals.extend(bindings.iter().cloned());
+
+                self.resolve_block(body);
+                self.locals.truncate(depth);
+            }
+            AstNode::Quotation { body, .. } => self.resolve_block(body),
+            AstNode::IfExpression {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.resolve_block(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.resolve_block(else_branch);
+                }
+            }
+            AstNode::WhileExpression {
+                condition, body, ..
+            } => {
+                self.resolve_block(condition);
+                self.resolve_block(body);
+            }
+            AstNode::TryExpression { body, handler, .. } => {
+                self.resolve_block(body);
+                self.resolve_block(handler);
+            }
+            AstNode::MatchExpression { cases, location } => {
+                if let Err(error) = self.resolve_match(cases, location) {
+                    self.errors.push(error);
+                }
+
+                for case in cases {
+                    self.resolve_block(&case.body);
+                }
+            }
+            AstNode::FieldGet { field, location } | AstNode::FieldSet { field, location }
+                if !self.program.has_field(field) =>
+            {
+                self.errors.push(ResolverError::UnknownField {
+                    field: field.clone(),
+                    location: location.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Rejects a local named after a global variable, which would make
+    /// `name @` and `name !` ambiguous inside its scope.
+    fn check_shadowing(&mut self, name: &str, location: &Location) {
+        if self.program.global_index(name).is_some() {
+            self.errors.push(ResolverError::ShadowedGlobal {
+                name: name.to_string(),
+                location: location.clone(),
+            });
         }
     }
 
-    pub fn resolve(&mut self, ast: Vec<AstNode>) -> Result<Program, ResolverError> {
+    /// Allocates a block for every function declared among `nodes`, so that
+    /// they are visible to the whole body, rejecting a second declaration of
+    /// the same name in the same scope.
+    fn declare_functions(&mut self, nodes: &[AstNode]) {
+        for node in nodes {
+            if let AstNode::FunctionDeclaration { name, location, .. } = node {
+                let key = scoped_name(&self.scope, name);
+
+                if let Some(previous) = self.declarations.get(&key) {
+                    self.errors.push(ResolverError::DuplicateFunction {
+                        name: name.clone(),
+                        previous: previous.clone(),
+                        location: location.clone(),
+                    });
+
+                    continue;
+                }
+
+                if self.program.natives.index(name).is_some() {
+                    self.errors.push(ResolverError::ShadowedNative {
+                        name: name.clone(),
+                        location: location.clone(),
+                    });
+                }
+
+                self.declarations.insert(key.clone(), location.clone());
+
+                let block = self.program.add_block();
+                self.program.add_function(&key, block);
+
+                if key == "main" {
+                    self.program.set_entry_point(block);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the constants and allocates the globals declared at the top
+    /// level, in order, so that a constant can be defined in terms of the ones
+    /// declared before it.
+    fn declare_globals(&mut self, nodes: &[AstNode]) {
+        for node in nodes {
+            let (name, location) = match node {
+                AstNode::ConstDeclaration { name, location, .. }
+                | AstNode::VarDeclaration { name, location } => (name, location),
+                _ => continue,
+            };
+
+            if self.program.constants.contains_key(name)
+                || self.program.global_index(name).is_some()
+                || self.program.functions.contains_key(name)
+                || self.program.struct_index(name).is_some()
+                || self.program.enum_index(name).is_some()
+                || self.program.variant_index(name).is_some()
+            {
+                self.errors.push(ResolverError::DuplicateGlobal {
+                    name: name.clone(),
+                    location: location.clone(),
+                });
+
+                continue;
+            }
+
+            match node {
+                AstNode::ConstDeclaration { body, .. } => {
+                    match constant::evaluate(name, body, location, &self.program.constants) {
+                        Ok(value) => self.program.add_constant(name, value),
+                        Err(error) => self.errors.push(error),
+                    }
+                }
+                _ => {
+                    self.program.add_global(name);
+                }
+            }
+        }
+    }
+
+    /// Resolves the nodes of the top level or of a function body, the only
+    /// places where functions can be declared.
+    fn resolve_body(&mut self, nodes: &[AstNode]) {
+        // Duplicate declarations are only resolved once, against the block
+        // allocated for the first one.
+        let mut resolved = Vec::new();
+
+        for node in nodes {
+            match node {
+                AstNode::FunctionDeclaration { name, body, .. } => {
+                    if resolved.contains(&name) {
+                        continue;
+                    }
+
+                    resolved.push(name);
+                    self.resolve_function(name, body);
+                }
+                // Registered by `declare_globals` and `resolve`.
+                AstNode::ConstDeclaration { .. }
+                | AstNode::VarDeclaration { .. }
+                | AstNode::StructDeclaration { .. }
+                | AstNode::EnumDeclaration { .. }
+                    if self.scope.is_empty() => {}
+                node => self.resolve_node(node),
+            }
+        }
+    }
+
+    /// Resolves a function body in its own scope: it sees the functions
+    /// declared in it and in the functions enclosing it, but not the `let`
+    /// bindings around its declaration.
+    fn resolve_function(&mut self, name: &str, body: &ast::Block) {
+        let key = scoped_name(&self.scope, name);
+        let old_scope = std::mem::replace(&mut self.scope, key);
+        let old_locals = std::mem::take(&mut self.locals);
+
+        self.current_block = self.program.functions[&self.scope];
+        self.declare_functions(&body.nodes);
+        self.resolve_body(&body.nodes);
+
+        self.scope = old_scope;
+        self.locals = old_locals;
+    }
+
+    fn resolve_struct(
+        &mut self,
+        name: &str,
+        fields: &[String],
+        location: &Location,
+    ) -> Result<(), ResolverError> {
+        // `Error` is taken by the struct runtime errors are caught as.
+        if self.program.struct_index(name).is_some()
+            || self.program.enum_index(name).is_some()
+            || self.program.variant_index(name).is_some()
+            || self.program.functions.contains_key(name)
+        {
+            return Err(ResolverError::DuplicateStruct {
+                name: name.to_string(),
+                location: location.clone(),
+            });
+        }
+
+        for (index, field) in fields.iter().enumerate() {
+            if fields[..index].contains(field) {
+                return Err(ResolverError::DuplicateField {
+                    structure: name.to_string(),
+                    field: field.clone(),
+                    location: location.clone(),
+                });
+            }
+        }
+
+        self.program.add_struct(name, fields);
+        Ok(())
+    }
+
+    fn resolve_enum(
+        &mut self,
+        name: &str,
+        variants: &[VariantDeclaration],
+        location: &Location,
+    ) -> Result<(), ResolverError> {
+        if self.program.struct_index(name).is_some() || self.program.enum_index(name).is_some() {
+            return Err(ResolverError::DuplicateEnum {
+                name: name.to_string(),
+                location: location.clone(),
+            });
+        }
+
+        let mut definitions: Vec<VariantDefinition> = Vec::new();
+
+        for variant in variants {
+            if self.program.variant_index(&variant.name).is_some()
+                || self.program.struct_index(&variant.name).is_some()
+                || self.program.functions.contains_key(&variant.name)
+                || definitions
+                    .iter()
+                    .any(|definition| definition.name == variant.name)
+            {
+                return Err(ResolverError::DuplicateVariant {
+                    name: variant.name.clone(),
+                    location: location.clone(),
+                });
+            }
+
+            definitions.push(VariantDefinition {
+                name: variant.name.clone(),
+                fields: variant.fields.len(),
+            });
+        }
+
+        self.program.add_enum(name, definitions);
+        Ok(())
+    }
+
+    /// Checks that every case names a distinct variant of the same enum, and
+    /// warns when some variants of that enum are not covered.
+    fn resolve_match(
+        &mut self,
+        cases: &[MatchCase],
+        location: &Location,
+    ) -> Result<(), ResolverError> {
+        if cases.is_empty() {
+            return Err(ResolverError::EmptyMatch(location.clone()));
+        }
+
+        let mut enumeration: Option<usize> = None;
+        let mut covered = Vec::new();
+
+        for case in cases {
+            let (type_id, tag) = match self.program.variant_index(&case.variant) {
+                Some(index) => index,
+                None => {
+                    return Err(ResolverError::UnknownVariant {
+                        name: case.variant.clone(),
+                        location: case.location.clone(),
+                    })
+                }
+            };
+
+            match enumeration {
+                Some(expected) if expected != type_id => {
+                    return Err(ResolverError::MixedMatch {
+                        enumeration: self.program.enums[expected].name.clone(),
+                        variant: case.variant.clone(),
+                        location: case.location.clone(),
+                    })
+                }
+                _ => enumeration = Some(type_id),
+            }
+
+            if covered.contains(&tag) {
+                return Err(ResolverError::DuplicateCase {
+                    name: case.variant.clone(),
+                    location: case.location.clone(),
+                });
+            }
+
+            covered.push(tag);
+        }
+
+        if let Some(type_id) = enumeration {
+            let definition = &self.program.enums[type_id];
+            let missing = definition
+                .variants
+                .iter()
+                .enumerate()
+                .filter(|(tag, _)| !covered.contains(tag))
+                .map(|(_, variant)| variant.name.as_str())
+                .collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                self.warnings.push(Diagnostic::warning(
+                    format!(
+                        "Non-exhaustive match on `{}`: missing {}",
+                        definition.name,
+                        missing.join(", ")
+                    ),
+                    location,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the whole program, collecting every error found instead of
+    /// stopping at the first one.
+    pub fn resolve(&mut self, ast: Vec<AstNode>) -> Result<Program, Vec<ResolverError>> {
+        // Functions and types are registered first so that they can be
+        // referenced before their declaration, and so that a type cannot
+        // take the name of a function.
+        self.declare_functions(&ast);
+
         for node in &ast {
-            self.resolve_node(node)?;
+            let result = match node {
+                AstNode::StructDeclaration {
+                    name,
+                    fields,
+                    location,
+                } => self.resolve_struct(name, fields, location),
+                AstNode::EnumDeclaration {
+                    name,
+                    variants,
+                    location,
+                } => self.resolve_enum(name, variants, location),
+                _ => Ok(()),
+            };
+
+            if let Err(error) = result {
+                self.errors.push(error);
+            }
+        }
+
+        self.declare_globals(&ast);
+
+        if !self.program.functions.contains_key("main") {
+            // The nodes of the main file come last, after those of its imports.
+            let location = ast.last().map(|node| node.location().clone());
+            self.errors.push(ResolverError::MissingMain(location));
+        }
+
+        self.resolve_body(&ast);
+
+        // Stack effects are only meaningful once every name is known.
+        if self.errors.is_empty() {
+            if let Err(error) = EffectChecker::new(&ast, &self.program.natives).check() {
+                self.errors.push(error);
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
         }
 
         Ok(self.program.clone())
     }
 }
+
+/// Collects the functions declared among `nodes` and, recursively, those
+/// nested in their bodies.
+fn collect_functions<'a>(
+    nodes: &'a [AstNode],
+    scope: &str,
+    functions: &mut HashMap<String, FunctionInfo<'a>>,
+) {
+    for node in nodes {
+        if let AstNode::FunctionDeclaration {
+            name,
+            signature,
+            body,
+            location,
+        } = node
+        {
+            let key = scoped_name(scope, name);
+            collect_functions(&body.nodes, &key, functions);
+
+            functions
+                .entry(key)
+                .or_insert((signature.as_ref(), body, location));
+        }
+    }
+}
+
+fn signature_effect(signature: &Signature) -> StackEffect {
+    StackEffect::new(signature.inputs.len(), signature.outputs.len())
+}
+
+type FunctionInfo<'a> = (Option<&'a Signature>, &'a ast::Block, &'a Location);
+
+/// Computes the net stack effect of every function body, checking it against
+/// the declared signature when there is one. Effects that depend on values only
+/// known at runtime (such as applying a closure) are treated as unknown and
+/// are not checked.
+struct EffectChecker<'a> {
+    /// Functions keyed as in `Program::functions`, nested ones included.
+    functions: HashMap<String, FunctionInfo<'a>>,
+    structs: HashMap<&'a str, usize>,
+    variants: HashMap<&'a str, usize>,
+    constants: HashSet<&'a str>,
+    globals: HashSet<&'a str>,
+    natives: Natives,
+    effects: HashMap<String, Option<StackEffect>>,
+    in_progress: Vec<String>,
+    scope: String,
+    locals: Vec<&'a str>,
+}
+
+impl<'a> EffectChecker<'a> {
+    fn new(ast: &'a [AstNode], natives: &Natives) -> Self {
+        let mut functions = HashMap::new();
+        let mut structs = HashMap::new();
+        let mut variants = HashMap::new();
+        let mut constants = HashSet::new();
+        let mut globals = HashSet::new();
+
+        collect_functions(ast, "", &mut functions);
+
+        for node in ast {
+            match node {
+                AstNode::StructDeclaration { name, fields, .. } => {
+                    structs.insert(name.as_str(), fields.len());
+                }
+                AstNode::EnumDeclaration {
+                    variants: declarations,
+                    ..
+                } => {
+                    for variant in declarations {
+                        variants.insert(variant.name.as_str(), variant.fields.len());
+                    }
+                }
+                AstNode::ConstDeclaration { name, .. } => {
+                    constants.insert(name.as_str());
+                }
+                AstNode::VarDeclaration { name, .. } => {
+                    globals.insert(name.as_str());
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            functions,
+            structs,
+            variants,
+            constants,
+            globals,
+            natives: natives.clone(),
+            effects: HashMap::new(),
+            in_progress: Vec::new(),
+            scope: String::new(),
+            locals: Vec::new(),
+        }
+    }
+
+    fn check(&mut self) -> Result<(), ResolverError> {
+        let keys = self.functions.keys().cloned().collect::<Vec<_>>();
+
+        for key in keys {
+            self.function_effect(&key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds the key of the function a name refers to from the current scope,
+    /// following the same rules as `Program::lookup_function`.
+    fn lookup_function(&self, name: &str) -> Option<String> {
+        let mut scope = self.scope.as_str();
+
+        loop {
+            let key = scoped_name(scope, name);
+
+            if self.functions.contains_key(&key) {
+                return Some(key);
+            }
+
+            if scope.is_empty() {
+                return None;
+            }
+
+            scope = scope.rsplit_once('/').map_or("", |(parent, _)| parent);
+        }
+    }
+
+    fn function_effect(&mut self, key: &str) -> Result<Option<StackEffect>, ResolverError> {
+        if let Some(effect) = self.effects.get(key) {
+            return Ok(*effect);
+        }
+
+        let (signature, body, location) = match self.functions.get(key) {
+            Some(info) => *info,
+            None => return Ok(None),
+        };
+
+        let declared = signature.map(signature_effect);
+
+        // Recursive calls can only be checked against a declared signature.
+        if self.in_progress.iter().any(|name| name == key) {
+            return Ok(declared);
+        }
+
+        self.in_progress.push(key.to_string());
+        let scope = std::mem::replace(&mut self.scope, key.to_string());
+        let locals = std::mem::take(&mut self.locals);
+
+        let actual = self.block_effect(body);
+
+        self.locals = locals;
+        self.scope = scope;
+        self.in_progress.pop();
+
+        let effect = match (declared, actual?) {
+            (Some(declared), Some(actual)) => {
+                if actual.inputs > declared.inputs
+                    || (!actual.diverges && actual.net() != declared.net())
+                {
+                    return Err(ResolverError::SignatureMismatch {
+                        name: key.to_string(),
+                        declared,
+                        actual,
+                        location: location.clone(),
+                    });
+                }
+
+                Some(declared)
+            }
+            (declared, actual) => declared.or(actual),
+        };
+
+        self.effects.insert(key.to_string(), effect);
+        Ok(effect)
+    }
+
+    fn word_effect(&mut self, name: &'a str) -> Result<Option<StackEffect>, ResolverError> {
+        if let Some(kind) = builtin_instruction(name) {
+            return Ok(kind.stack_effect());
+        }
+
+        if let Some(key) = self.lookup_function(name) {
+            return self.function_effect(&key);
+        }
+
+        if let Some(index) = self.natives.index(name) {
+            let native = self.natives.get(index);
+            return Ok(Some(StackEffect::new(native.inputs, native.outputs)));
+        }
+
+        match self.structs.get(name).or(self.variants.get(name)) {
+            Some(fields) => Ok(Some(StackEffect::new(*fields, 1))),
+            None => Ok(None),
+        }
+    }
+
+    fn block_effect(
+        &mut self,
+        block: &'a ast::Block,
+    ) -> Result<Option<StackEffect>, ResolverError> {
+        let mut effect = Some(StackEffect::identity());
+
+        // Every node is visited, even once the effect is unknown, so that
+        // errors in nested branches are still reported.
+        for node in &block.nodes {
+            let node_effect = self.node_effect(node)?;
+
+            effect = match (effect, node_effect) {
+                (Some(effect), Some(node_effect)) => Some(effect.then(&node_effect)),
+                _ => None,
+            };
+        }
+
+        Ok(effect)
+    }
+
+    fn node_effect(&mut self, node: &'a AstNode) -> Result<Option<StackEffect>, ResolverError> {
+        let condition = StackEffect::new(1, 0);
+
+        match node {
+            AstNode::IntegerLiteral(..)
+            | AstNode::FloatLiteral(..)
+            | AstNode::StringLiteral(..)
+            | AstNode::BooleanLiteral(..) => Ok(Some(StackEffect::new(0, 1))),
+            AstNode::Identifier(name, _) => {
+                if self.locals.contains(&name.as_str()) || self.constants.contains(name.as_str()) {
+                    return Ok(Some(StackEffect::new(0, 1)));
+                }
+
+                self.word_effect(name)
+            }
+            AstNode::GlobalStore { name, .. } => {
+                if self.globals.contains(name.as_str()) {
+                    return Ok(Some(StackEffect::new(1, 0)));
+                }
+
+                let value = if self.locals.contains(&name.as_str()) {
+                    Some(StackEffect::new(0, 1))
+                } else {
+                    self.word_effect(name)?
+                };
+
+                Ok(value.map(|value| value.then(&StackEffect::new(1, 1))))
+            }
+            AstNode::GlobalFetch { .. } => Ok(Some(StackEffect::new(0, 1))),
+            AstNode::FunctionCall { name, .. } => self.word_effect(name),
+            AstNode::Quotation { body, .. } => {
+                self.block_effect(body)?;
+                Ok(Some(StackEffect::new(0, 1)))
+            }
+            AstNode::LetDeclaration { bindings, body, .. } => {
+                let depth = self.locals.len();
+                self.locals
+                    .extend(bindings.iter().map(|name| name.as_str()));
+
+                let effect = self.block_effect(body);
+                self.locals.truncate(depth);
+
+                Ok(effect?.map(|effect| StackEffect::new(bindings.len(), 0).then(&effect)))
+            }
+            AstNode::IfExpression {
+                then_branch,
+                else_branch,
+                location,
+            } => {
+                let then_effect = self.block_effect(then_branch)?;
+                let else_effect = match else_branch {
+                    Some(else_branch) => self.block_effect(else_branch)?,
+                    None => Some(StackEffect::identity()),
+                };
+
+                match (then_effect, else_effect) {
+                    (Some(then_effect), Some(else_effect)) => {
+                        if !then_effect.joins(&else_effect) {
+                            return Err(ResolverError::BranchEffectMismatch {
+                                then_effect,
+                                else_effect,
+                                location: location.clone(),
+                            });
+                        }
+
+                        Ok(Some(condition.then(&then_effect.join(&else_effect))))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            AstNode::WhileExpression {
+                condition: condition_block,
+                body,
+                location,
+            } => {
+                let condition_effect = self.block_effect(condition_block)?;
+                let body_effect = self.block_effect(body)?;
+
+                match (condition_effect, body_effect) {
+                    (Some(condition_effect), Some(body_effect)) => {
+                        let head = condition_effect.then(&condition);
+                        let iteration = head.then(&body_effect);
+
+                        if !iteration.diverges && iteration.net() != 0 {
+                            return Err(ResolverError::UnbalancedLoop {
+                                effect: iteration,
+                                location: location.clone(),
+                            });
+                        }
+
+                        Ok(Some(head.widen(iteration.inputs)))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            AstNode::ForExpression {
+                variable,
+                start,
+                end,
+                body,
+                location,
+                ..
+            } => {
+                let mut setup = Some(StackEffect::identity());
+
+                for bound in [start, end] {
+                    let effect = self.node_effect(bound)?;
+
+                    if effect.is_some_and(|effect| effect.inputs != 0 || effect.outputs != 1) {
+                        return Err(ResolverError::InvalidRangeBound(bound.location().clone()));
+                    }
+
+                    setup = setup.zip(effect).map(|(setup, effect)| setup.then(&effect));
+                }
+
+                self.locals.push(variable);
+                let body_effect = self.block_effect(body);
+                self.locals.pop();
+
+                match (setup, body_effect?) {
+                    (Some(setup), Some(body_effect)) => {
+                        if !body_effect.diverges && body_effect.net() != 0 {
+                            return Err(ResolverError::UnbalancedLoop {
+                                effect: body_effect,
+                                location: location.clone(),
+                            });
+                        }
+
+                        // The body may not run at all, so the loop returns
+                        // even when the body does not.
+                        let body_effect = StackEffect::new(body_effect.inputs, body_effect.inputs);
+                        Ok(Some(setup.then(&StackEffect::new(2, 0)).then(&body_effect)))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            AstNode::TryExpression {
+                body,
+                handler,
+                location,
+            } => {
+                let body_effect = self.block_effect(body)?;
+                let handler_effect = self.block_effect(handler)?;
+
+                // Values the body consumed are gone by the time an error is
+                // caught, so the handler would not see the stack it expects.
+                if let Some(effect) = body_effect.filter(|effect| effect.inputs > 0) {
+                    return Err(ResolverError::TryConsumesStack {
+                        effect,
+                        location: location.clone(),
+                    });
+                }
+
+                match (body_effect, handler_effect) {
+                    (Some(body_effect), Some(handler_effect)) => {
+                        let handler_effect = StackEffect::new(0, 1).then(&handler_effect);
+
+                        if !body_effect.joins(&handler_effect) {
+                            return Err(ResolverError::TryEffectMismatch {
+                                body: body_effect,
+                                handler: handler_effect,
+                                location: location.clone(),
+                            });
+                        }
+
+                        Ok(Some(body_effect.join(&handler_effect)))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            AstNode::FieldGet { .. } => Ok(Some(StackEffect::new(1, 1))),
+            AstNode::FieldSet { .. } => Ok(Some(StackEffect::new(2, 1))),
+            AstNode::MatchExpression { cases, location } => {
+                let mut merged: Option<StackEffect> = None;
+                let mut known = true;
+
+                for case in cases {
+                    let fields = self.variants.get(case.variant.as_str()).copied();
+                    let body = self.block_effect(&case.body)?;
+
+                    let effect = match (fields, body) {
+                        (Some(fields), Some(body)) => StackEffect::new(0, fields).then(&body),
+                        _ => {
+                            known = false;
+                            continue;
+                        }
+                    };
+
+                    merged = match merged {
+                        Some(first) if !first.joins(&effect) => {
+                            return Err(ResolverError::CaseEffectMismatch {
+                                first,
+                                other: effect,
+                                location: location.clone(),
+                            })
+                        }
+                        Some(first) => Some(first.join(&effect)),
+                        None => Some(effect),
+                    };
+                }
+
+                Ok(match (known, merged) {
+                    (true, Some(merged)) => Some(condition.then(&merged)),
+                    _ => None,
+                })
+            }
+            AstNode::FunctionDeclaration { .. }
+            | AstNode::StructDeclaration { .. }
+            | AstNode::EnumDeclaration { .. }
+            | AstNode::ImportDeclaration { .. }
+            | AstNode::ConstDeclaration { .. }
+            | AstNode::VarDeclaration { .. } => Ok(Some(StackEffect::identity())),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::token::location::Location;
+
+#[derive(Debug, Clone)]
+pub enum Breakpoint {
+    /// Stops when the program reaches `line` of a file whose path ends with
+    /// `file`.
+    Line { file: String, line: usize },
+    /// Stops on entry to the function stored at `block`.
+    Function { name: String, block: usize },
+}
+
+impl Breakpoint {
+    pub fn matches_line(&self, location: &Location) -> bool {
+        match self {
+            Breakpoint::Line { file, line } => {
+                *line == location.line
+                    && (*location.file == **file || location.file.ends_with(&format!("/{}", file)))
+            }
+            Breakpoint::Function { .. } => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Line { file, line } => write!(f, "{}:{}", file, line),
+            Breakpoint::Function { name, .. } => write!(f, "`{}`", name),
+        }
+    }
+}
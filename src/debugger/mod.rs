@@ -0,0 +1,351 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    sync::Arc,
+};
+
+use crate::{
+    compiler::instruction::InstructionKind, runtime::Interpreter, token::location::Location,
+};
+
+use self::breakpoint::Breakpoint;
+
+pub mod breakpoint;
+
+const HELP: &str = "\
+Commands:
+  break <file:line|function>  stop at a line or on entry to a function (b)
+  delete <number>             remove a breakpoint
+  breakpoints                 list the breakpoints
+  step                        run until the next source line, entering calls (s)
+  next                        run until the next source line in this function (n)
+  finish                      run until the current function returns
+  continue                    run until a breakpoint or the end (c)
+  stack                       print the value stack
+  frames                      print the call frames (bt)
+  help                        print this message
+  quit                        stop debugging (q)
+An empty line repeats the previous command.";
+
+/// How far `Debugger::resume` runs the program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Step,
+    Next,
+    Finish,
+    Continue,
+}
+
+/// A source line together with the call depth it was reached at, used to tell
+/// when the program moves on to another line.
+type Position = (Option<(Arc<str>, usize)>, usize);
+
+/// An interactive command loop that runs a program instruction by instruction,
+/// stopping at breakpoints and showing the source line, stack and call frames.
+pub struct Debugger {
+    interpreter: Interpreter,
+    breakpoints: Vec<Breakpoint>,
+    labels: Vec<String>,
+    /// Lines of the source files shown so far, `None` for unreadable ones
+    /// such as the standard library modules.
+    sources: HashMap<Arc<str>, Option<Vec<String>>>,
+    finished: bool,
+}
+
+impl Debugger {
+    pub fn new(interpreter: Interpreter) -> Self {
+        let labels = interpreter.program().block_labels();
+
+        Self {
+            interpreter,
+            breakpoints: Vec::new(),
+            labels,
+            sources: HashMap::new(),
+            finished: false,
+        }
+    }
+
+    /// Reads commands from `input` until the user quits or the input ends.
+    pub fn run(&mut self, mut input: impl BufRead, output: impl Write) -> io::Result<()> {
+        self.command_loop(|_, line| input.read_line(line), output)
+    }
+
+    /// Reads commands from the input of the program being debugged, such as
+    /// standard input, so that commands and the lines the program reads come
+    /// in turn from the same reader instead of two buffers competing for it.
+    pub fn run_with_program_input(&mut self, output: impl Write) -> io::Result<()> {
+        self.command_loop(
+            |debugger, line| debugger.interpreter.input().read_line(line),
+            output,
+        )
+    }
+
+    fn command_loop(
+        &mut self,
+        mut read_line: impl FnMut(&mut Self, &mut String) -> io::Result<usize>,
+        mut output: impl Write,
+    ) -> io::Result<()> {
+        writeln!(output, "Type `help` for a list of commands.")?;
+        self.show_location(&mut output)?;
+
+        let mut previous = String::new();
+
+        loop {
+            write!(output, "(debug) ")?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if read_line(self, &mut line)? == 0 {
+                return Ok(());
+            }
+
+            let line = match line.trim() {
+                "" => previous.clone(),
+                line => line.to_string(),
+            };
+
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or_default();
+            let argument = words.next();
+
+            match (command, argument) {
+                ("" | "help", _) => writeln!(output, "{}", HELP)?,
+                ("quit" | "q", _) => return Ok(()),
+                ("break" | "b", Some(target)) => self.add_breakpoint(target, &mut output)?,
+                ("delete", Some(number)) => self.delete_breakpoint(number, &mut output)?,
+                ("breakpoints", _) => {
+                    for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+                        writeln!(output, "{}: {}", index + 1, breakpoint)?;
+                    }
+                }
+                ("step" | "s", _) => self.resume(Mode::Step, &mut output)?,
+                ("next" | "n", _) => self.resume(Mode::Next, &mut output)?,
+                ("finish", _) => self.resume(Mode::Finish, &mut output)?,
+                ("continue" | "c", _) => self.resume(Mode::Continue, &mut output)?,
+                ("stack", _) => self.show_stack(&mut output)?,
+                ("frames" | "bt", _) => self.show_frames(&mut output)?,
+                _ => writeln!(output, "Unknown command `{}`, type `help`", line)?,
+            }
+
+            previous = line;
+        }
+    }
+
+    fn add_breakpoint(&mut self, target: &str, output: &mut impl Write) -> io::Result<()> {
+        let breakpoint = match target.rsplit_once(':') {
+            Some((file, line)) if line.parse::<usize>().is_ok() => Breakpoint::Line {
+                file: file.to_string(),
+                line: line.parse().unwrap_or_default(),
+            },
+            _ => {
+                // Nested functions can also be named without their enclosing ones.
+                let functions = &self.interpreter.program().functions;
+                let matches = match functions.get_key_value(target) {
+                    Some(function) => vec![function],
+                    None => functions
+                        .iter()
+                        .filter(|(name, _)| name.ends_with(&format!("/{}", target)))
+                        .collect(),
+                };
+
+                match matches.as_slice() {
+                    [(name, &block)] => Breakpoint::Function {
+                        name: name.to_string(),
+                        block,
+                    },
+                    [] => return writeln!(output, "No function named `{}`", target),
+                    _ => {
+                        let mut names = matches
+                            .iter()
+                            .map(|(name, _)| format!("`{}`", name))
+                            .collect::<Vec<_>>();
+                        names.sort();
+
+                        return writeln!(output, "`{}` could be {}", target, names.join(", "));
+                    }
+                }
+            }
+        };
+
+        writeln!(
+            output,
+            "Breakpoint {} at {}",
+            self.breakpoints.len() + 1,
+            breakpoint
+        )?;
+        self.breakpoints.push(breakpoint);
+
+        Ok(())
+    }
+
+    fn delete_breakpoint(&mut self, number: &str, output: &mut impl Write) -> io::Result<()> {
+        match number.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= self.breakpoints.len() => {
+                let breakpoint = self.breakpoints.remove(number - 1);
+                writeln!(output, "Deleted breakpoint at {}", breakpoint)
+            }
+            _ => writeln!(output, "No breakpoint number {}", number),
+        }
+    }
+
+    fn position(&self) -> Position {
+        let line = self
+            .interpreter
+            .location()
+            .map(|location| (location.file.clone(), location.line));
+
+        (line, self.interpreter.call_depth())
+    }
+
+    /// Whether a breakpoint stops the program at its next instruction, having
+    /// come from `previous`.
+    fn at_breakpoint(&self, previous: &Position) -> bool {
+        let (block, ip) = self.interpreter.position();
+        let location = match self.interpreter.location() {
+            Some(location) => location,
+            None => return false,
+        };
+
+        // A line breakpoint stops once on entering the line, not at each of
+        // its instructions nor when a call made from the line returns. After
+        // a return, the line left is the one of the call just before.
+        let (line, depth) = self.position();
+        let entered = if depth < previous.1 {
+            let call = ip.checked_sub(1).and_then(|ip| {
+                self.interpreter.program().blocks[block]
+                    .instructions
+                    .get(ip)
+            });
+            call.is_none_or(|call| call.location.line != location.line)
+        } else {
+            line != previous.0
+        };
+
+        self.breakpoints.iter().any(|breakpoint| match breakpoint {
+            Breakpoint::Function { block: entry, .. } => block == *entry && ip == 0,
+            Breakpoint::Line { .. } => entered && breakpoint.matches_line(location),
+        })
+    }
+
+    fn at_exit(&self) -> bool {
+        let (block, ip) = self.interpreter.position();
+
+        self.interpreter
+            .program()
+            .blocks
+            .get(block)
+            .and_then(|block| block.instructions.get(ip))
+            .is_some_and(|instruction| {
+                matches!(
+                    instruction.kind,
+                    InstructionKind::Return | InstructionKind::Halt
+                )
+            })
+    }
+
+    fn resume(&mut self, mode: Mode, output: &mut impl Write) -> io::Result<()> {
+        if self.finished {
+            return writeln!(output, "The program is not running");
+        }
+
+        let start = self.position();
+        let mut previous = start.clone();
+
+        loop {
+            match self.interpreter.step() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.finished = true;
+                    return writeln!(output, "The program finished");
+                }
+                Err(error) => {
+                    self.finished = true;
+                    return writeln!(output, "{}", error);
+                }
+            }
+
+            let current = self.position();
+            let (line, depth) = &current;
+
+            // Returns and the final halt are located at the declaration of
+            // their function, and the standard library has no source to show,
+            // so stepping passes over them.
+            let steppable = !self.at_exit()
+                && line
+                    .as_ref()
+                    .is_some_and(|(file, _)| !file.starts_with("std::"));
+
+            let stop = match mode {
+                Mode::Step => steppable && *line != start.0,
+                Mode::Next => {
+                    steppable && (*depth < start.1 || (*depth == start.1 && *line != start.0))
+                }
+                Mode::Finish => *depth < start.1,
+                Mode::Continue => false,
+            };
+
+            if stop || self.at_breakpoint(&previous) {
+                return self.show_location(output);
+            }
+
+            previous = current;
+        }
+    }
+
+    fn show_location(&mut self, output: &mut impl Write) -> io::Result<()> {
+        let location = match self.interpreter.location() {
+            Some(location) => location.clone(),
+            None => return writeln!(output, "The program is not running"),
+        };
+
+        writeln!(output, "Stopped at {}", location)?;
+
+        if let Some(source) = self.source_line(&location) {
+            writeln!(output, "{:>5} | {}", location.line, source)?;
+        }
+
+        Ok(())
+    }
+
+    fn source_line(&mut self, location: &Location) -> Option<String> {
+        let lines = self
+            .sources
+            .entry(location.file.clone())
+            .or_insert_with(|| {
+                fs::read_to_string(&*location.file)
+                    .ok()
+                    .map(|source| source.lines().map(|line| line.to_string()).collect())
+            });
+
+        lines.as_ref()?.get(location.line.checked_sub(1)?).cloned()
+    }
+
+    fn show_stack(&self, output: &mut impl Write) -> io::Result<()> {
+        let values = self
+            .interpreter
+            .stack()
+            .iter()
+            .map(|value| self.interpreter.format_value(value))
+            .collect::<Vec<_>>();
+
+        writeln!(output, "[{}]", values.join(", "))
+    }
+
+    fn show_frames(&self, output: &mut impl Write) -> io::Result<()> {
+        if self.finished {
+            return writeln!(output, "The program is not running");
+        }
+
+        for (index, (block, location)) in self.interpreter.call_stack().iter().enumerate() {
+            let name = &self.labels[*block];
+
+            match location {
+                Some(location) => writeln!(output, "#{} `{}` at {}", index, name, location)?,
+                None => writeln!(output, "#{} `{}`", index, name)?,
+            }
+        }
+
+        Ok(())
+    }
+}
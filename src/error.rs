@@ -0,0 +1,58 @@
+use crate::{
+    compiler::error::{CompilerError, ResolverError},
+    loader::error::LoaderError,
+    runtime::error::RuntimeError,
+};
+
+/// Any error produced while compiling or running a program.
+#[derive(Debug, Clone)]
+pub enum Error {
+    Loader(LoaderError),
+    Resolver(Vec<ResolverError>),
+    Compiler(CompilerError),
+    Runtime(RuntimeError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Loader(error) => write!(f, "{}", error),
+            Error::Resolver(errors) => {
+                let messages = errors
+                    .iter()
+                    .map(|error| error.to_string())
+                    .collect::<Vec<_>>();
+
+                write!(f, "{}", messages.join("\n"))
+            }
+            Error::Compiler(error) => write!(f, "{}", error),
+            Error::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<LoaderError> for Error {
+    fn from(error: LoaderError) -> Self {
+        Error::Loader(error)
+    }
+}
+
+impl From<Vec<ResolverError>> for Error {
+    fn from(errors: Vec<ResolverError>) -> Self {
+        Error::Resolver(errors)
+    }
+}
+
+impl From<CompilerError> for Error {
+    fn from(error: CompilerError) -> Self {
+        Error::Compiler(error)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        Error::Runtime(error)
+    }
+}
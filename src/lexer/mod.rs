@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::token::{location::Location, Token, TokenKind};
 
 const RESERVED_CHARS: [u8; 7] = [b'(', b')', b'[', b']', b'{', b'}', b'.'];
@@ -11,29 +13,27 @@ pub struct Lexer {
     column: usize,
 
     source: String,
+    file: Arc<str>,
 }
 
 // TODO: Add support for comments
 // TODO: Add support for binary literals, octal literals, and hex literals
 // TODO: Add support for escape sequences in strings
-// TODO: Add error reporting mechanism
 impl Lexer {
-    pub fn new(source: &str) -> Lexer {
+    pub fn new(source: &str, file: &str) -> Lexer {
         Lexer {
             start: 0,
             current: 0,
             line: 1,
             column: 1,
             source: source.to_string(),
+            file: Arc::from(file),
         }
     }
 
-    fn is_at_end(&self) -> bool {
-        self.current >= self.source.len()
-    }
-
     fn current_location(&self) -> Location {
         Location {
+            file: self.file.clone(),
             line: self.line,
             column: self.column - (self.current - self.start),
         }
@@ -89,7 +89,8 @@ impl Lexer {
             self.advance();
         }
 
-        if let Some(b'.') = self.peek(0) {
+        // `0..10` is a range, not the float `0.` followed by `.10`
+        if self.peek(0) == Some(b'.') && self.peek(1) != Some(b'.') {
             self.advance();
 
             while let Some(c) = self.peek(0) {
@@ -107,14 +108,14 @@ impl Lexer {
 
             match value {
                 Ok(value) => TokenKind::Float(value),
-                Err(_) => TokenKind::Error,
+                Err(_) => TokenKind::Error(format!("Invalid float `{}`", lexeme)),
             }
         } else {
             let value = lexeme.parse::<i64>();
 
             match value {
                 Ok(value) => TokenKind::Integer(value),
-                Err(_) => TokenKind::Error,
+                Err(_) => TokenKind::Error(format!("Invalid integer `{}`", lexeme)),
             }
         };
 
@@ -127,6 +128,11 @@ impl Lexer {
                 break;
             }
 
+            // `::` separates the segments of a qualified name
+            if c == b':' && self.peek(1) == Some(b':') {
+                break;
+            }
+
             self.advance();
         }
 
@@ -148,6 +154,17 @@ impl Lexer {
             "false" => TokenKind::False,
             "call" => TokenKind::Call,
             "do" => TokenKind::Do,
+            "struct" => TokenKind::Struct,
+            "enum" => TokenKind::Enum,
+            "match" => TokenKind::Match,
+            "case" => TokenKind::Case,
+            "import" => TokenKind::Import,
+            "const" => TokenKind::Const,
+            "var" => TokenKind::Var,
+            "for" => TokenKind::For,
+            "step" => TokenKind::Step,
+            "try" => TokenKind::Try,
+            "catch" => TokenKind::Catch,
             _ => TokenKind::Identifier(lexeme.to_string()),
         };
 
@@ -161,7 +178,7 @@ impl Lexer {
             let c = self.peek(0);
 
             match c {
-                None => panic!("Unexpected end of string"),
+                None => return self.new_token(TokenKind::Error("Unterminated string".to_string())),
                 Some(c) => match c {
                     b'"' => {
                         self.advance();
@@ -172,7 +189,10 @@ impl Lexer {
 
                         let c = self.peek(0);
                         match c {
-                            None => panic!("Unexpected end of string"),
+                            None => {
+                                return self
+                                    .new_token(TokenKind::Error("Unterminated string".to_string()))
+                            }
                             Some(b'"') => {
                                 self.advance();
                                 buffer.push('"');
@@ -197,9 +217,12 @@ impl Lexer {
                                 self.advance();
                                 buffer.push('\0');
                             }
-                            Some(_) => {
+                            Some(c) => {
                                 // TODO: add unicode escape sequences and hex escape sequences
-                                panic!("Invalid escape sequence");
+                                return self.new_token(TokenKind::Error(format!(
+                                    "Invalid escape sequence `\\{}`",
+                                    c as char
+                                )));
                             }
                         }
                     }
@@ -289,7 +312,7 @@ impl Lexer {
                 _ if c.is_ascii_digit() => self.handle_numbers(),
                 _ => self.handle_identifiers(),
             },
-            None => self.new_token(TokenKind::EOF),
+            None => self.new_token(TokenKind::Eof),
         }
     }
 
@@ -300,7 +323,7 @@ impl Lexer {
             let token = self.next();
             tokens.push(token.clone());
 
-            if token.kind == TokenKind::EOF {
+            if token.kind == TokenKind::Eof {
                 break;
             }
         }
@@ -0,0 +1,100 @@
+//! A small stack-based language: a loader, compiler and interpreter that can
+//! be embedded in Rust programs.
+//!
+//! ```
+//! use stack_based_lang::{compile_source, Interpreter, Natives, Value};
+//!
+//! let mut natives = Natives::new();
+//! natives.register("triple", 1, 1, |_, arguments| match arguments[0] {
+//!     Value::I64(n) => Ok(vec![Value::I64(n * 3)]),
+//!     _ => Err("expected an integer".to_string()),
+//! })?;
+//!
+//! let source = "
+//!     fun sextuple (n -> n) do triple 2 * end
+//!     fun main do end
+//! ";
+//!
+//! let compilation = compile_source(source, "script.shark", true, &natives)?;
+//! let mut interpreter = Interpreter::new(compilation.program);
+//!
+//! interpreter.push(Value::I64(7));
+//! interpreter.call("sextuple")?;
+//! assert!(matches!(interpreter.pop(), Some(Value::I64(42))));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod analysis;
+mod compiler;
+mod debugger;
+mod error;
+mod lexer;
+mod loader;
+mod parser;
+mod runtime;
+mod token;
+
+use crate::{
+    compiler::{resolver::Resolver, Compiler},
+    loader::Loader,
+    parser::ast::AstNode,
+};
+
+pub use crate::{
+    analysis::{depth::DepthAnalyzer, diagnostic::Diagnostic, types::TypeChecker},
+    compiler::{
+        error::{CompilerError, ResolverError},
+        program::Program,
+    },
+    debugger::Debugger,
+    error::Error,
+    loader::error::LoaderError,
+    parser::error::ParserError,
+    runtime::{
+        config::InterpreterConfig,
+        error::{BacktraceFrame, RuntimeError},
+        native::{Natives, ReservedName},
+        profile::{Cost, FunctionCost, Profiler},
+        trace::Tracer,
+        value::Value,
+        Interpreter,
+    },
+    token::location::Location,
+};
+
+/// A compiled program, along with the warnings found while resolving it.
+#[derive(Debug, Clone)]
+pub struct Compilation {
+    pub program: Program,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Compiles the file at `path` and everything it imports, loading the
+/// standard library first when `prelude` is set. The program can call the
+/// words registered in `natives`.
+pub fn compile_file(path: &str, prelude: bool, natives: &Natives) -> Result<Compilation, Error> {
+    let ast = Loader::new(prelude).load(path)?;
+    compile_ast(ast, natives)
+}
+
+/// Compiles a program held in memory. `name` identifies it in error locations.
+pub fn compile_source(
+    source: &str,
+    name: &str,
+    prelude: bool,
+    natives: &Natives,
+) -> Result<Compilation, Error> {
+    let ast = Loader::new(prelude).load_str(source, name)?;
+    compile_ast(ast, natives)
+}
+
+fn compile_ast(ast: Vec<AstNode>, natives: &Natives) -> Result<Compilation, Error> {
+    let mut resolver = Resolver::with_natives(natives.clone());
+    let program = resolver.resolve(ast.clone())?;
+    let program = Compiler::new(program).compile(ast)?;
+
+    Ok(Compilation {
+        program,
+        warnings: resolver.warnings().to_vec(),
+    })
+}
@@ -0,0 +1,46 @@
+use crate::{parser::error::ParserError, token::location::Location};
+
+#[derive(Debug, Clone)]
+pub enum LoaderError {
+    Io {
+        path: String,
+        message: String,
+        location: Option<Location>,
+    },
+    ImportCycle {
+        chain: Vec<String>,
+        location: Location,
+    },
+    Parser(ParserError),
+}
+
+impl std::fmt::Display for LoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoaderError::Io {
+                path,
+                message,
+                location,
+            } => match location {
+                Some(location) => write!(
+                    f,
+                    "Unable to read `{}`: {} (imported at {})",
+                    path, message, location
+                ),
+                None => write!(f, "Unable to read `{}`: {}", path, message),
+            },
+            LoaderError::ImportCycle { chain, location } => {
+                write!(f, "Import cycle {} at {}", chain.join(" -> "), location)
+            }
+            LoaderError::Parser(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for LoaderError {}
+
+impl From<ParserError> for LoaderError {
+    fn from(error: ParserError) -> Self {
+        LoaderError::Parser(error)
+    }
+}
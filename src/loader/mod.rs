@@ -0,0 +1,437 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use crate::{
+    lexer::Lexer,
+    parser::{
+        ast::{AstNode, Block, ImportSource},
+        Parser,
+    },
+    token::location::Location,
+};
+
+use self::error::LoaderError;
+
+pub mod error;
+mod prelude;
+
+/// Reads a source file along with everything it imports, producing a single
+/// AST for the resolver. Every file is loaded once; the functions of imported
+/// files are qualified with the module name (`lines::println`), while the
+/// declarations of the main file are left as they are.
+///
+/// With the prelude enabled, the bundled standard library is loaded first, its
+/// functions qualified as `std::strings::concat`, and they can also be called
+/// unqualified from any file that doesn't declare a function of the same name.
+#[derive(Debug)]
+pub struct Loader {
+    loaded: HashSet<String>,
+    loading: Vec<(String, String)>,
+    modules: Vec<AstNode>,
+
+    prelude: bool,
+    aliases: HashMap<String, String>,
+}
+
+impl Loader {
+    pub fn new(prelude: bool) -> Self {
+        Self {
+            loaded: HashSet::new(),
+            loading: Vec::new(),
+            modules: Vec::new(),
+
+            prelude,
+            aliases: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<Vec<AstNode>, LoaderError> {
+        self.load_prelude()?;
+        let nodes = self.load_file(Path::new(path), None, None)?;
+
+        Ok(self.finish(nodes))
+    }
+
+    /// Loads a program from source held in memory. `name` is used in error
+    /// locations, and imports are resolved relative to the current directory.
+    pub fn load_str(&mut self, source: &str, name: &str) -> Result<Vec<AstNode>, LoaderError> {
+        self.load_prelude()?;
+        let nodes = self.load_source(
+            name.to_string(),
+            name.to_string(),
+            source,
+            Path::new(""),
+            None,
+            None,
+        )?;
+
+        Ok(self.finish(nodes))
+    }
+
+    fn finish(&mut self, nodes: Vec<AstNode>) -> Vec<AstNode> {
+        let mut ast = std::mem::take(&mut self.modules);
+        ast.extend(nodes);
+
+        ast
+    }
+
+    fn load_prelude(&mut self) -> Result<(), LoaderError> {
+        if self.prelude {
+            for (module, _) in prelude::MODULES {
+                let nodes = self.load_std(module, None)?;
+
+                for node in &nodes {
+                    if let AstNode::FunctionDeclaration { name, .. } = node {
+                        let alias = name.trim_start_matches(&format!("std::{}::", module));
+                        self.aliases.insert(alias.to_string(), name.clone());
+                    }
+                }
+
+                self.modules.extend(nodes);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_std(
+        &mut self,
+        module: &str,
+        location: Option<&Location>,
+    ) -> Result<Vec<AstNode>, LoaderError> {
+        let key = format!("std::{}", module);
+
+        let source = match prelude::module_source(module) {
+            Some(source) => source,
+            None => {
+                return Err(LoaderError::Io {
+                    path: key,
+                    message: "no such module in the standard library".to_string(),
+                    location: location.cloned(),
+                })
+            }
+        };
+
+        self.load_source(
+            key.clone(),
+            key.clone(),
+            source,
+            Path::new(""),
+            Some(&key),
+            location,
+        )
+    }
+
+    fn load_file(
+        &mut self,
+        path: &Path,
+        namespace: Option<&str>,
+        location: Option<&Location>,
+    ) -> Result<Vec<AstNode>, LoaderError> {
+        let display = path.display().to_string();
+        let io_error = |error: std::io::Error| LoaderError::Io {
+            path: display.clone(),
+            message: error.to_string(),
+            location: location.cloned(),
+        };
+
+        let canonical = fs::canonicalize(path).map_err(io_error)?;
+        let source = fs::read_to_string(path).map_err(io_error)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        self.load_source(
+            canonical.display().to_string(),
+            display,
+            &source,
+            directory,
+            namespace,
+            location,
+        )
+    }
+
+    fn load_source(
+        &mut self,
+        key: String,
+        display: String,
+        source: &str,
+        directory: &Path,
+        namespace: Option<&str>,
+        location: Option<&Location>,
+    ) -> Result<Vec<AstNode>, LoaderError> {
+        if let Some(start) = self.loading.iter().position(|(loading, _)| loading == &key) {
+            let mut chain = self.loading[start..]
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect::<Vec<_>>();
+            chain.push(display);
+
+            return Err(LoaderError::ImportCycle {
+                chain,
+                location: location.cloned().expect("the main file can't be imported"),
+            });
+        }
+
+        if self.loaded.contains(&key) {
+            return Ok(Vec::new());
+        }
+
+        let tokens = Lexer::new(source, &display).lex();
+        let ast = Parser::new(tokens).parse()?;
+
+        self.loading.push((key.clone(), display));
+
+        let mut nodes = Vec::new();
+
+        for node in ast {
+            match node {
+                AstNode::ImportDeclaration { source, location } => {
+                    let imported = match &source {
+                        ImportSource::Module(module) if module.starts_with("std::") => {
+                            self.load_std(&module["std::".len()..], Some(&location))?
+                        }
+                        ImportSource::File(file) => {
+                            let target = directory.join(file);
+                            let module = target
+                                .file_stem()
+                                .map(|stem| stem.to_string_lossy().to_string())
+                                .unwrap_or_default();
+
+                            self.load_file(&target, Some(&module), Some(&location))?
+                        }
+                        ImportSource::Module(module) => {
+                            let segments = module.split("::").collect::<Vec<_>>();
+                            let target = directory.join(segments.join("/")).with_extension("shark");
+
+                            let module = segments[segments.len() - 1];
+                            self.load_file(&target, Some(module), Some(&location))?
+                        }
+                    };
+
+                    self.modules.extend(imported);
+                }
+                node => nodes.push(node),
+            }
+        }
+
+        self.loading.pop();
+        self.loaded.insert(key);
+
+        let declared = nodes
+            .iter()
+            .filter_map(|node| match node {
+                AstNode::FunctionDeclaration { name, .. }
+                | AstNode::ConstDeclaration { name, .. }
+                | AstNode::VarDeclaration { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        // Functions declared in the file shadow the prelude ones of the same name.
+        let mut renames = self
+            .aliases
+            .iter()
+            .filter(|(alias, _)| !declared.contains(*alias))
+            .map(|(alias, name)| (alias.clone(), name.clone()))
+            .collect::<HashMap<_, _>>();
+
+        if let Some(namespace) = namespace {
+            for name in declared {
+                let qualified = format!("{}::{}", namespace, name);
+                renames.insert(name, qualified);
+            }
+        }
+
+        let renamer = Renamer { renames: &renames };
+
+        Ok(nodes
+            .into_iter()
+            .map(|node| renamer.node(node, &mut Bound::default()))
+            .collect())
+    }
+}
+
+/// The names that hide a renamed function: functions nested in the enclosing
+/// bodies and `let` bindings.
+#[derive(Default)]
+struct Bound {
+    functions: Vec<String>,
+    locals: Vec<String>,
+}
+
+impl Bound {
+    fn contains(&self, name: &String) -> bool {
+        self.functions.contains(name) || self.locals.contains(name)
+    }
+}
+
+/// Renames references to functions, leaving alone the ones shadowed by a
+/// nested function or a local binding.
+struct Renamer<'a> {
+    renames: &'a HashMap<String, String>,
+}
+
+impl Renamer<'_> {
+    fn name(&self, name: String, bound: &Bound) -> String {
+        match self.renames.get(&name) {
+            Some(renamed) if !bound.contains(&name) => renamed.clone(),
+            _ => name,
+        }
+    }
+
+    fn block(&self, block: Block, bound: &mut Bound) -> Block {
+        Block::new(
+            block
+                .nodes
+                .into_iter()
+                .map(|node| self.node(node, bound))
+                .collect(),
+        )
+    }
+
+    fn node(&self, node: AstNode, bound: &mut Bound) -> AstNode {
+        match node {
+            AstNode::Identifier(name, location) => {
+                AstNode::Identifier(self.name(name, bound), location)
+            }
+            AstNode::FunctionCall { name, location } => AstNode::FunctionCall {
+                name: self.name(name, bound),
+                location,
+            },
+            AstNode::FunctionDeclaration {
+                name,
+                signature,
+                body,
+                location,
+            } => {
+                // A nested function's body sees the functions declared in it and
+                // around it, but not the surrounding `let` bindings.
+                let mut inner = Bound {
+                    functions: bound.functions.clone(),
+                    locals: Vec::new(),
+                };
+                inner
+                    .functions
+                    .extend(body.nodes.iter().filter_map(|node| match node {
+                        AstNode::FunctionDeclaration { name, .. } => Some(name.clone()),
+                        _ => None,
+                    }));
+
+                AstNode::FunctionDeclaration {
+                    name: self.name(name, bound),
+                    signature,
+                    body: self.block(body, &mut inner),
+                    location,
+                }
+            }
+            AstNode::ConstDeclaration {
+                name,
+                body,
+                location,
+            } => AstNode::ConstDeclaration {
+                name: self.name(name, bound),
+                body: self.block(body, bound),
+                location,
+            },
+            AstNode::VarDeclaration { name, location } => AstNode::VarDeclaration {
+                name: self.name(name, bound),
+                location,
+            },
+            AstNode::GlobalStore { name, location } => AstNode::GlobalStore {
+                name: self.name(name, bound),
+                location,
+            },
+            // A fetch always refers to a global, even if a local has the same name.
+            AstNode::GlobalFetch { name, location } => AstNode::GlobalFetch {
+                name: self.name(name, &Bound::default()),
+                location,
+            },
+            AstNode::LetDeclaration {
+                bindings,
+                body,
+                location,
+            } => {
+                let depth = bound.locals.len();
+                bound.locals.extend(bindings.iter().cloned());
+
+                let body = self.block(body, bound);
+                bound.locals.truncate(depth);
+
+                AstNode::LetDeclaration {
+                    bindings,
+                    body,
+                    location,
+                }
+            }
+            AstNode::ForExpression {
+                variable,
+                start,
+                end,
+                step,
+                body,
+                location,
+            } => {
+                let start = Box::new(self.node(*start, bound));
+                let end = Box::new(self.node(*end, bound));
+                let step = step.map(|step| Box::new(self.node(*step, bound)));
+
+                bound.locals.push(variable.clone());
+                let body = self.block(body, bound);
+                bound.locals.pop();
+
+                AstNode::ForExpression {
+                    variable,
+                    start,
+                    end,
+                    step,
+                    body,
+                    location,
+                }
+            }
+            AstNode::TryExpression {
+                body,
+                handler,
+                location,
+            } => AstNode::TryExpression {
+                body: self.block(body, bound),
+                handler: self.block(handler, bound),
+                location,
+            },
+            AstNode::Quotation { body, location } => AstNode::Quotation {
+                body: self.block(body, bound),
+                location,
+            },
+            AstNode::IfExpression {
+                then_branch,
+                else_branch,
+                location,
+            } => AstNode::IfExpression {
+                then_branch: self.block(then_branch, bound),
+                else_branch: else_branch.map(|block| self.block(block, bound)),
+                location,
+            },
+            AstNode::WhileExpression {
+                condition,
+                body,
+                location,
+            } => AstNode::WhileExpression {
+                condition: self.block(condition, bound),
+                body: self.block(body, bound),
+                location,
+            },
+            AstNode::MatchExpression { cases, location } => AstNode::MatchExpression {
+                cases: cases
+                    .into_iter()
+                    .map(|mut case| {
+                        case.body = self.block(case.body, bound);
+                        case
+                    })
+                    .collect(),
+                location,
+            },
+            node => node,
+        }
+    }
+}
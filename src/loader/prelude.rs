@@ -0,0 +1,16 @@
+/// The standard library modules bundled with the binary, importable as
+/// `std::<name>` and loaded into every program unless the prelude is disabled.
+pub const MODULES: [(&str, &str); 5] = [
+    ("strings", include_str!("../../std/strings.shark")),
+    ("math", include_str!("../../std/math.shark")),
+    ("lists", include_str!("../../std/lists.shark")),
+    ("iteration", include_str!("../../std/iteration.shark")),
+    ("assertions", include_str!("../../std/assertions.shark")),
+];
+
+pub fn module_source(name: &str) -> Option<&'static str> {
+    MODULES
+        .iter()
+        .find(|(module, _)| *module == name)
+        .map(|(_, source)| *source)
+}
@@ -1,49 +1,141 @@
-mod compiler;
-mod lexer;
-mod parser;
-mod runtime;
-mod token;
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    process,
+};
 
-use compiler::Compiler;
-use lexer::Lexer;
-use parser::Parser;
-use std::{env, fs};
+use stack_based_lang::{
+    compile_file, Debugger, DepthAnalyzer, Diagnostic, Error, Interpreter, Natives, Profiler,
+    Program, Tracer, TypeChecker,
+};
 
-use crate::{compiler::resolver::Resolver, runtime::Interpreter};
+fn print_usage(binary: &str) {
+    println!(
+        "Usage: {} [run] [--no-prelude] [--sandbox] [--trace] [--trace-only=<functions>] [--trace-file=<file>] [--profile] [--profile-folded=<file>] <file> [-- <args>...]",
+        binary
+    );
+    println!("       {} check [--types] [--no-prelude] <file>", binary);
+    println!(
+        "       {} debug [--no-prelude] [--sandbox] <file> [-- <args>...]",
+        binary
+    );
+}
 
-fn main() {
-    let args = env::args().skip(1).collect::<Vec<String>>();
+fn compile(path: &str, prelude: bool) -> Option<Program> {
+    match compile_file(path, prelude, &Natives::new()) {
+        Ok(compilation) => {
+            report(&compilation.warnings);
+            Some(compilation.program)
+        }
+        Err(Error::Resolver(errors)) => {
+            for error in errors {
+                println!("{}", error);
+            }
 
-    if args.len() != 1 {
-        println!("Usage: {} <file>", args[0]);
-        return;
+            None
+        }
+        Err(error) => {
+            println!("{}", error);
+            None
+        }
     }
+}
 
-    let source = fs::read_to_string(&args[0]).expect("Unable to read file");
-    let mut lexer = Lexer::new(&source);
-    let tokens = lexer.lex();
+/// Prints the diagnostics, returning whether any of them is an error.
+fn report(diagnostics: &[Diagnostic]) -> bool {
+    for diagnostic in diagnostics {
+        println!("{}", diagnostic);
+    }
 
-    // println!("{:#?}", &tokens);
+    diagnostics.iter().any(|diagnostic| diagnostic.is_error())
+}
 
-    let mut parser = Parser::new(tokens);
-    let ast = parser.parse();
+/// Creates the tracer for `run --trace`, writing to `file` or to stderr and
+/// limited to the comma-separated `functions` if given.
+fn tracer(file: Option<&str>, functions: Option<&str>) -> Option<Tracer> {
+    let tracer = match file {
+        Some(file) => match File::create(file) {
+            Ok(output) => Tracer::new(BufWriter::new(output)),
+            Err(error) => {
+                println!("Unable to create `{}`: {}", file, error);
+                return None;
+            }
+        },
+        None => Tracer::new(io::stderr()),
+    };
 
-    let mut resolver = Resolver::new();
-    let program = match resolver.resolve(ast.clone()) {
-        Ok(program) => program,
-        Err(error) => {
-            println!("{}", error);
-            return;
+    Some(match functions {
+        Some(functions) => {
+            tracer.with_functions(functions.split(',').map(|name| name.to_string()).collect())
         }
-    };
+        None => tracer,
+    })
+}
 
-    let mut compiler = Compiler::new(program);
-    let program = compiler.compile(ast).expect("Unable to compile program");
+/// Prints the report of `run --profile` to stderr, and writes the folded call
+/// stacks for flamegraph tools to `folded` if given.
+fn profile(profiler: &Profiler, folded: Option<&str>) {
+    const LINES: usize = 20;
+
+    let mut output = io::stderr();
+    if let Err(error) = profiler.write_report(&mut output, LINES) {
+        println!("Unable to write the profile: {}", error);
+    }
+
+    if let Some(file) = folded {
+        let written = File::create(file).and_then(|output| {
+            let mut output = BufWriter::new(output);
+            profiler.write_folded(&mut output)?;
+            output.flush()
+        });
+
+        if let Err(error) = written {
+            println!("Unable to write `{}`: {}", file, error);
+        }
+    }
+}
+
+fn run(
+    path: &str,
+    prelude: bool,
+    sandbox: bool,
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+    folded: Option<&str>,
+    script_args: Vec<String>,
+) {
+    let program = match compile(path, prelude) {
+        Some(program) => program,
+        None => return,
+    };
 
     println!("{:#?}", &program);
 
-    let mut interpreter = Interpreter::new(program.clone());
-    let result = match interpreter.interpret() {
+    if report(&DepthAnalyzer::new(&program).analyze()) {
+        return;
+    }
+
+    let mut interpreter = Interpreter::new(program.clone())
+        .with_sandbox(sandbox)
+        .with_args(script_args);
+
+    if let Some(tracer) = tracer {
+        interpreter = interpreter.with_tracer(tracer);
+    }
+
+    if let Some(profiler) = profiler {
+        interpreter = interpreter.with_profiler(profiler);
+    }
+
+    let result = interpreter.interpret();
+
+    // The profile covers the instructions run up to an error too.
+    if let Some(profiler) = interpreter.profiler() {
+        profile(profiler, folded);
+    }
+
+    let result = match result {
         Ok(result) => result,
         Err(error) => {
             println!("{}", error);
@@ -53,3 +145,108 @@ fn main() {
 
     println!("{:#?}", result);
 }
+
+fn debug(path: &str, prelude: bool, sandbox: bool, script_args: Vec<String>) {
+    let program = match compile(path, prelude) {
+        Some(program) => program,
+        None => return,
+    };
+
+    let interpreter = Interpreter::new(program)
+        .with_sandbox(sandbox)
+        .with_args(script_args);
+
+    // Commands and the program's own input both come from stdin.
+    if let Err(error) = Debugger::new(interpreter).run_with_program_input(io::stdout()) {
+        println!("{}", error);
+    }
+}
+
+/// Checks the program at `path`, returning whether it is free of errors.
+fn check(path: &str, types: bool, prelude: bool) -> bool {
+    let program = match compile(path, prelude) {
+        Some(program) => program,
+        None => return false,
+    };
+
+    let mut diagnostics = DepthAnalyzer::new(&program).analyze();
+
+    if types {
+        diagnostics.extend(TypeChecker::new(&program).check());
+    }
+
+    if report(&diagnostics) {
+        return false;
+    }
+
+    println!("No problems found in {}", path);
+    true
+}
+
+fn main() {
+    let binary = env::args().next().unwrap_or_default();
+    let mut args = env::args().skip(1).collect::<Vec<String>>();
+
+    // Everything after `--` is passed to the script.
+    let script_args = match args.iter().position(|arg| arg == "--") {
+        Some(index) => args.split_off(index).into_iter().skip(1).collect(),
+        None => Vec::new(),
+    };
+
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>();
+
+    let prelude = !args.contains(&"--no-prelude");
+    let types = args.contains(&"--types");
+    let sandbox = args.contains(&"--sandbox");
+
+    let trace_file = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--trace-file="));
+    let trace_only = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--trace-only="));
+    let trace = args.contains(&"--trace") || trace_file.is_some() || trace_only.is_some();
+
+    let profile_folded = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--profile-folded="));
+    let profile = args.contains(&"--profile") || profile_folded.is_some();
+
+    let args = args
+        .into_iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<&str>>();
+
+    match args.as_slice() {
+        ["run", path] | [path] if !types => {
+            let tracer = if trace {
+                match tracer(trace_file, trace_only) {
+                    Some(tracer) => Some(tracer),
+                    None => return,
+                }
+            } else {
+                None
+            };
+
+            let profiler = profile.then(Profiler::new);
+
+            run(
+                path,
+                prelude,
+                sandbox,
+                tracer,
+                profiler,
+                profile_folded,
+                script_args,
+            )
+        }
+        // A failed check exits with an error status, for use in scripts.
+        ["check", path] => {
+            if !check(path, types, prelude) {
+                process::exit(1);
+            }
+        }
+        ["debug", path] => debug(path, prelude, sandbox, script_args),
+        _ => print_usage(&binary),
+    }
+}
@@ -11,6 +11,40 @@ impl Block {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct VariantDeclaration {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchCase {
+    pub variant: String,
+    pub body: Block,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportSource {
+    /// `import "path/to/lib.shark"`, relative to the importing file.
+    File(String),
+    /// `import std::strings`, resolved to `std/strings.shark`.
+    Module(String),
+}
+
+/// A Forth-style stack effect comment, e.g. `(a b -> sum)`.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+impl Signature {
+    pub fn new(inputs: Vec<String>, outputs: Vec<String>) -> Signature {
+        Signature { inputs, outputs }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AstNode {
     IntegerLiteral(i64, Location),
@@ -38,12 +72,91 @@ pub enum AstNode {
 
     FunctionDeclaration {
         name: String,
+        signature: Option<Signature>,
         body: Block,
         location: Location,
     },
 
+    /// `for variable in start..end [step step] do body end`, counting from
+    /// `start` up to (or down to, with a negative step) `end` excluded.
+    ForExpression {
+        variable: String,
+        start: Box<AstNode>,
+        end: Box<AstNode>,
+        step: Option<Box<AstNode>>,
+        body: Block,
+        location: Location,
+    },
+
+    /// `try body catch handler end`: runs `handler` with the error on top of the
+    /// stack if anything in `body` throws.
+    TryExpression {
+        body: Block,
+        handler: Block,
+        location: Location,
+    },
+
     LetDeclaration {
         bindings: Vec<String>,
+        body: Block,
+        location: Location,
+    },
+
+    Quotation {
+        body: Block,
+        location: Location,
+    },
+
+    StructDeclaration {
+        name: String,
+        fields: Vec<String>,
+        location: Location,
+    },
+
+    FieldGet {
+        field: String,
+        location: Location,
+    },
+
+    FieldSet {
+        field: String,
+        location: Location,
+    },
+
+    EnumDeclaration {
+        name: String,
+        variants: Vec<VariantDeclaration>,
+        location: Location,
+    },
+
+    MatchExpression {
+        cases: Vec<MatchCase>,
+        location: Location,
+    },
+
+    ImportDeclaration {
+        source: ImportSource,
+        location: Location,
+    },
+
+    ConstDeclaration {
+        name: String,
+        body: Block,
+        location: Location,
+    },
+
+    VarDeclaration {
+        name: String,
+        location: Location,
+    },
+
+    GlobalStore {
+        name: String,
+        location: Location,
+    },
+
+    GlobalFetch {
+        name: String,
         location: Location,
     },
 }
@@ -60,7 +173,20 @@ impl AstNode {
             AstNode::IfExpression { location, .. } => location,
             AstNode::WhileExpression { location, .. } => location,
             AstNode::FunctionDeclaration { location, .. } => location,
+            AstNode::ForExpression { location, .. } => location,
+            AstNode::TryExpression { location, .. } => location,
             AstNode::LetDeclaration { location, .. } => location,
+            AstNode::Quotation { location, .. } => location,
+            AstNode::StructDeclaration { location, .. } => location,
+            AstNode::FieldGet { location, .. } => location,
+            AstNode::FieldSet { location, .. } => location,
+            AstNode::EnumDeclaration { location, .. } => location,
+            AstNode::MatchExpression { location, .. } => location,
+            AstNode::ImportDeclaration { location, .. } => location,
+            AstNode::ConstDeclaration { location, .. } => location,
+            AstNode::VarDeclaration { location, .. } => location,
+            AstNode::GlobalStore { location, .. } => location,
+            AstNode::GlobalFetch { location, .. } => location,
         }
     }
 }
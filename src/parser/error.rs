@@ -0,0 +1,54 @@
+use crate::token::{location::Location, TokenKind};
+
+#[derive(Debug, Clone)]
+pub enum ParserError {
+    UnexpectedToken {
+        expected: String,
+        found: TokenKind,
+        location: Location,
+    },
+    InvalidToken {
+        message: String,
+        location: Location,
+    },
+    UnexpectedEnd {
+        expected: String,
+        location: Location,
+    },
+}
+
+impl ParserError {
+    pub fn location(&self) -> &Location {
+        match self {
+            ParserError::UnexpectedToken { location, .. }
+            | ParserError::InvalidToken { location, .. }
+            | ParserError::UnexpectedEnd { location, .. } => location,
+        }
+    }
+}
+
+impl std::fmt::Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParserError::UnexpectedToken {
+                expected,
+                found,
+                location,
+            } => write!(
+                f,
+                "Expected {}, found {:?} at {}",
+                expected, found, location
+            ),
+            ParserError::InvalidToken { message, location } => {
+                write!(f, "{} at {}", message, location)
+            }
+            ParserError::UnexpectedEnd { expected, location } => write!(
+                f,
+                "Expected {}, found the end of the file at {}",
+                expected, location
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
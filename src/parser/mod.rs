@@ -1,10 +1,12 @@
-use core::panic;
-
 use crate::token::{location::Location, Token, TokenKind};
 
-use self::ast::{AstNode, Block};
+use self::{
+    ast::{AstNode, Block, ImportSource, MatchCase, Signature, VariantDeclaration},
+    error::ParserError,
+};
 
 pub mod ast;
+pub mod error;
 
 #[derive(Debug)]
 pub struct Parser {
@@ -59,248 +61,488 @@ impl Parser {
 
     fn is_at_end(&self) -> bool {
         self.peek(0)
-            .map_or(false, |token| token.kind == TokenKind::EOF)
+            .is_none_or(|token| token.kind == TokenKind::Eof)
+    }
+
+    fn check(&self, kind: &TokenKind) -> bool {
+        self.peek(0).is_some_and(|token| &token.kind == kind)
+    }
+
+    /// The error for finding the current token where `expected` should be.
+    fn unexpected(&self, expected: &str) -> ParserError {
+        let token = self
+            .peek(0)
+            .or(self.tokens.last())
+            .expect("the tokens end with `Eof`");
+        let location = token.location.clone();
+
+        match &token.kind {
+            TokenKind::Eof => ParserError::UnexpectedEnd {
+                expected: expected.to_string(),
+                location,
+            },
+            TokenKind::Error(message) => ParserError::InvalidToken {
+                message: message.clone(),
+                location,
+            },
+            kind => ParserError::UnexpectedToken {
+                expected: expected.to_string(),
+                found: kind.clone(),
+                location,
+            },
+        }
     }
 
-    fn consume_identifier(&mut self) -> Option<String> {
+    fn consume_identifier(&mut self) -> Result<Option<String>, ParserError> {
         match self.peek(0) {
             Some(token) => match token.kind.clone() {
                 TokenKind::Identifier(lexeme) => {
                     self.advance();
-                    Some(lexeme.clone())
+                    self.parse_qualified_name(lexeme).map(Some)
                 }
-                _ => None,
+                _ => Ok(None),
             },
-            None => None,
+            None => Ok(None),
         }
     }
 
-    fn consume(&mut self, kind: TokenKind) -> Option<&Token> {
-        if self.peek(0).map_or(false, |token| token.kind == kind) {
-            self.advance()
-        } else {
-            panic!("Expected token {:?}, got {:?}", kind, self.peek(0));
+    fn expect_identifier(&mut self, expected: &str) -> Result<String, ParserError> {
+        match self.consume_identifier()? {
+            Some(name) => Ok(name),
+            None => Err(self.unexpected(expected)),
         }
     }
 
-    fn consume_any_of(&mut self, kinds: &[TokenKind]) -> Option<&Token> {
-        if self
+    /// Joins `segment :: segment ...` following `first` into a single qualified name.
+    fn parse_qualified_name(&mut self, first: String) -> Result<String, ParserError> {
+        let mut name = first;
+
+        while self
             .peek(0)
-            .map_or(false, |token| kinds.contains(&token.kind))
+            .is_some_and(|token| token.kind == TokenKind::ColonColon)
         {
-            self.advance()
+            self.advance();
+
+            match self.peek(0).map(|token| token.kind.clone()) {
+                Some(TokenKind::Identifier(segment)) => {
+                    self.advance();
+
+                    name.push_str("::");
+                    name.push_str(&segment);
+                }
+                _ => return Err(self.unexpected("a name after `::`")),
+            }
+        }
+
+        Ok(name)
+    }
+
+    fn consume(&mut self, kind: TokenKind) -> Result<(), ParserError> {
+        if self.check(&kind) {
+            self.advance();
+            Ok(())
         } else {
-            panic!(
-                "Expected token {:?}, got {:?}",
-                kinds,
-                self.peek(0).map(|token| token.kind.clone())
-            );
+            Err(self.unexpected(&format!("{:?}", kind)))
         }
     }
 
-    fn parse_block(&mut self) -> Block {
+    /// Parses nodes up to, but not including, a `terminator`.
+    fn parse_nodes_until(&mut self, terminators: &[TokenKind]) -> Result<Block, ParserError> {
         let mut nodes = Vec::new();
 
-        loop {
-            match self.peek(0) {
-                Some(token) => match &token.kind {
-                    TokenKind::End => break,
-                    _ => nodes.push(self.parse_node()),
-                },
-                None => break,
-            }
+        while !self.is_at_end() && !terminators.iter().any(|kind| self.check(kind)) {
+            nodes.push(self.parse_node()?);
         }
 
-        self.consume(TokenKind::End);
-        Block::new(nodes)
+        Ok(Block::new(nodes))
     }
 
-    fn parse_function_definition(&mut self) -> AstNode {
+    fn parse_block(&mut self) -> Result<Block, ParserError> {
+        let block = self.parse_nodes_until(&[TokenKind::End])?;
+        self.consume(TokenKind::End)?;
+
+        Ok(block)
+    }
+
+    fn parse_function_definition(&mut self) -> Result<AstNode, ParserError> {
         let location = self.previous_location();
-        let name = match self.consume_identifier() {
-            Some(lexeme) => lexeme,
-            None => panic!("Unable to get function name"),
+        let name = self.expect_identifier("a function name")?;
+
+        let signature = match self.peek(0) {
+            Some(token) if token.kind == TokenKind::OpenParen => Some(self.parse_signature()?),
+            _ => None,
         };
 
-        self.consume(TokenKind::Do);
+        self.consume(TokenKind::Do)?;
 
-        let body = self.parse_block();
+        let body = self.parse_block()?;
         let location =
-            location.combine(body.nodes.last().map_or(&location, |node| &node.location()));
+            location.combine(body.nodes.last().map_or(&location, |node| node.location()));
 
-        AstNode::FunctionDeclaration {
+        Ok(AstNode::FunctionDeclaration {
             name,
+            signature,
             body,
             location,
-        }
+        })
     }
 
-    fn parse_while_condition(&mut self) -> Block {
-        let mut nodes = Vec::new();
+    fn parse_signature(&mut self) -> Result<Signature, ParserError> {
+        self.consume(TokenKind::OpenParen)?;
 
-        loop {
-            match self.peek(0) {
-                Some(token) => match &token.kind {
-                    TokenKind::Do => break,
-                    _ => nodes.push(self.parse_node()),
-                },
-                None => break,
-            }
+        let mut inputs = Vec::new();
+        while let Some(name) = self.consume_identifier()? {
+            inputs.push(name);
+        }
+
+        self.consume(TokenKind::Arrow)?;
+
+        let mut outputs = Vec::new();
+        while let Some(name) = self.consume_identifier()? {
+            outputs.push(name);
         }
 
-        self.consume(TokenKind::Do);
-        Block::new(nodes)
+        self.consume(TokenKind::CloseParen)?;
+        Ok(Signature::new(inputs, outputs))
     }
 
-    fn parse_while_expression(&mut self) -> AstNode {
+    fn parse_while_expression(&mut self) -> Result<AstNode, ParserError> {
         let location = self.previous_location();
-        let condition = self.parse_while_condition();
-        let body = self.parse_block();
+        let condition = self.parse_nodes_until(&[TokenKind::Do])?;
+        self.consume(TokenKind::Do)?;
+        let body = self.parse_block()?;
 
         let location =
-            location.combine(body.nodes.last().map_or(&location, |node| &node.location()));
+            location.combine(body.nodes.last().map_or(&location, |node| node.location()));
 
-        AstNode::WhileExpression {
+        Ok(AstNode::WhileExpression {
             condition,
             body,
             location,
-        }
+        })
     }
 
-    fn parse_operator(&mut self) -> AstNode {
-        let token = match self.previous() {
-            Some(token) => token,
-            None => panic!("No previous token"),
-        };
+    fn parse_for_expression(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let variable = self.expect_identifier("a loop variable name")?;
 
-        let function_name = self.get_function_name(&token.kind);
-        let function_name = match function_name {
-            Some(name) => name,
-            None => panic!("Unable to get function name"),
+        self.consume(TokenKind::In)?;
+        let start = Box::new(self.parse_node()?);
+        self.consume(TokenKind::DotDot)?;
+        let end = Box::new(self.parse_node()?);
+
+        let step = match self.peek(0).map(|token| &token.kind) {
+            Some(TokenKind::Step) => {
+                self.advance();
+                Some(Box::new(self.parse_node()?))
+            }
+            _ => None,
         };
 
-        AstNode::FunctionCall {
-            name: function_name,
-            location: token.location.clone(),
-        }
+        self.consume(TokenKind::Do)?;
+        let body = self.parse_block()?;
+
+        Ok(AstNode::ForExpression {
+            variable,
+            start,
+            end,
+            step,
+            body,
+            location,
+        })
     }
 
-    fn parse_if_block(&mut self) -> Block {
-        let mut nodes = Vec::new();
+    fn parse_try_expression(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let body = self.parse_nodes_until(&[TokenKind::Catch])?;
 
-        loop {
-            match self.peek(0) {
-                Some(token) => match &token.kind {
-                    TokenKind::Else | TokenKind::End => break,
-                    _ => nodes.push(self.parse_node()),
-                },
-                None => break,
-            }
-        }
+        self.consume(TokenKind::Catch)?;
+        let handler = self.parse_block()?;
 
-        Block::new(nodes)
+        Ok(AstNode::TryExpression {
+            body,
+            handler,
+            location,
+        })
     }
 
-    fn parse_if_expression(&mut self) -> AstNode {
+    fn parse_if_expression(&mut self) -> Result<AstNode, ParserError> {
         let location = self.previous_location();
-        let then_branch = self.parse_if_block();
-
-        let terminator = self.consume_any_of(&[TokenKind::Else, TokenKind::End]);
-        let else_branch = match terminator {
-            None => panic!(
-                "Expected token {:?}, got {:?}",
-                [TokenKind::Else, TokenKind::End],
-                self.peek(0)
-            ),
-            Some(token) => match token.kind {
-                TokenKind::Else => Some(self.parse_block()),
-                TokenKind::End => None,
-                _ => panic!(
-                    "Expected token {:?}, got {:?}",
-                    [TokenKind::Else, TokenKind::End],
-                    self.peek(0)
-                ),
-            },
+        let then_branch = self.parse_nodes_until(&[TokenKind::Else, TokenKind::End])?;
+
+        let else_branch = if self.check(&TokenKind::Else) {
+            self.advance();
+            Some(self.parse_block()?)
+        } else {
+            self.consume(TokenKind::End)?;
+            None
         };
 
         let then_branch_location = then_branch
             .nodes
             .last()
-            .map_or(&location, |node| &node.location());
+            .map_or(&location, |node| node.location());
 
         let location =
-            location.combine(else_branch.as_ref().map_or(&then_branch_location, |block| {
-                block
-                    .nodes
-                    .last()
-                    .map_or(&location, |node| &node.location())
+            location.combine(else_branch.as_ref().map_or(then_branch_location, |block| {
+                block.nodes.last().map_or(&location, |node| node.location())
             }));
 
-        AstNode::IfExpression {
+        Ok(AstNode::IfExpression {
             then_branch,
             else_branch,
             location,
+        })
+    }
+
+    fn parse_function_call(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let name = self.expect_identifier("a function name")?;
+
+        Ok(AstNode::FunctionCall { name, location })
+    }
+
+    fn parse_let_declaration(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let mut bindings = vec![self.expect_identifier("a binding name")?];
+
+        while let Some(name) = self.consume_identifier()? {
+            bindings.push(name);
         }
+
+        self.consume(TokenKind::In)?;
+
+        let body = self.parse_block()?;
+        let location =
+            location.combine(body.nodes.last().map_or(&location, |node| node.location()));
+
+        Ok(AstNode::LetDeclaration {
+            bindings,
+            body,
+            location,
+        })
     }
 
-    fn parse_function_call(&mut self) -> AstNode {
+    fn parse_quotation(&mut self) -> Result<AstNode, ParserError> {
         let location = self.previous_location();
-        let name = match self.consume_identifier() {
-            Some(lexeme) => lexeme,
-            None => panic!("Unable to get function name"),
-        };
+        let body = self.parse_nodes_until(&[TokenKind::CloseBracket])?;
 
-        AstNode::FunctionCall { name, location }
+        self.consume(TokenKind::CloseBracket)?;
+
+        Ok(AstNode::Quotation { body, location })
+    }
+
+    fn parse_struct_declaration(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let name = self.expect_identifier("a struct name")?;
+
+        let mut fields = Vec::new();
+        while let Some(field) = self.consume_identifier()? {
+            fields.push(field);
+        }
+
+        self.consume(TokenKind::End)?;
+
+        Ok(AstNode::StructDeclaration {
+            name,
+            fields,
+            location,
+        })
     }
 
-    fn parse_node(&mut self) -> AstNode {
-        let token = self.advance();
+    fn parse_field_access(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let field = self.expect_identifier("a field name")?;
+
+        Ok(match field.strip_suffix('!') {
+            Some(field) => AstNode::FieldSet {
+                field: field.to_string(),
+                location,
+            },
+            None => AstNode::FieldGet { field, location },
+        })
+    }
 
-        match token {
-            None => panic!("Unexpected end of file"),
-            Some(token) => match &token.kind {
-                TokenKind::Integer(i) => AstNode::IntegerLiteral(*i, token.location.clone()),
-                TokenKind::Float(f) => AstNode::FloatLiteral(*f, token.location.clone()),
-                TokenKind::String(s) => {
-                    AstNode::StringLiteral(s.to_string(), token.location.clone())
+    fn parse_enum_declaration(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let name = self.expect_identifier("an enum name")?;
+
+        let mut variants = Vec::new();
+
+        loop {
+            let variant = self.expect_identifier("a variant name")?;
+
+            let mut fields = Vec::new();
+            if self
+                .peek(0)
+                .is_some_and(|token| token.kind == TokenKind::OpenParen)
+            {
+                self.consume(TokenKind::OpenParen)?;
+
+                while let Some(field) = self.consume_identifier()? {
+                    fields.push(field);
                 }
-                TokenKind::True => AstNode::BooleanLiteral(true, token.location.clone()),
-                TokenKind::False => AstNode::BooleanLiteral(false, token.location.clone()),
-                TokenKind::Identifier(s) => {
-                    AstNode::Identifier(s.to_string(), token.location.clone())
+
+                self.consume(TokenKind::CloseParen)?;
+            }
+
+            variants.push(VariantDeclaration {
+                name: variant,
+                fields,
+            });
+
+            match self.peek(0).map(|token| token.kind.clone()) {
+                Some(TokenKind::Identifier(separator)) if separator == "|" => {
+                    self.advance();
                 }
-                TokenKind::Plus
-                | TokenKind::Minus
-                | TokenKind::Star
-                | TokenKind::Slash
-                | TokenKind::Percent
-                | TokenKind::Less
-                | TokenKind::LessEqual
-                | TokenKind::Greater
-                | TokenKind::GreaterEqual
-                | TokenKind::EqualEqual
-                | TokenKind::BangEqual
-                | TokenKind::And
-                | TokenKind::Or
-                | TokenKind::Not
-                | TokenKind::Bang => self.parse_operator(),
-                TokenKind::Fun => self.parse_function_definition(),
-                TokenKind::While => self.parse_while_expression(),
-                TokenKind::If => self.parse_if_expression(),
-                TokenKind::Call => self.parse_function_call(),
-                _ => todo!("parse_node is not implemented for {:?} yet", token.kind),
+                _ => break,
+            }
+        }
+
+        self.consume(TokenKind::End)?;
+
+        Ok(AstNode::EnumDeclaration {
+            name,
+            variants,
+            location,
+        })
+    }
+
+    fn parse_match_expression(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let mut cases = Vec::new();
+
+        while self
+            .peek(0)
+            .is_some_and(|token| token.kind == TokenKind::Case)
+        {
+            self.consume(TokenKind::Case)?;
+
+            let case_location = self.previous_location();
+            let variant = self.expect_identifier("a variant name")?;
+
+            self.consume(TokenKind::Do)?;
+
+            cases.push(MatchCase {
+                variant,
+                body: self.parse_block()?,
+                location: case_location,
+            });
+        }
+
+        self.consume(TokenKind::End)?;
+
+        Ok(AstNode::MatchExpression { cases, location })
+    }
+
+    fn parse_import(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+
+        let source = match self.peek(0).map(|token| token.kind.clone()) {
+            Some(TokenKind::String(path)) => {
+                self.advance();
+                ImportSource::File(path)
+            }
+            _ => ImportSource::Module(self.expect_identifier("a path or module name")?),
+        };
+
+        Ok(AstNode::ImportDeclaration { source, location })
+    }
+
+    /// Parses a name, turning `name !` and `name @` into a store to and a fetch
+    /// from a global. Whether `name` really is a global is only known once the
+    /// program is resolved, so `name !` may still turn out to be a negation.
+    fn parse_identifier(
+        &mut self,
+        name: String,
+        location: Location,
+    ) -> Result<AstNode, ParserError> {
+        let name = self.parse_qualified_name(name)?;
+
+        Ok(match self.peek(0).map(|token| &token.kind) {
+            Some(TokenKind::Bang) => {
+                self.advance();
+                AstNode::GlobalStore { name, location }
+            }
+            Some(TokenKind::Identifier(word)) if word == "@" => {
+                self.advance();
+                AstNode::GlobalFetch { name, location }
+            }
+            _ => AstNode::Identifier(name, location),
+        })
+    }
+
+    fn parse_const_declaration(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let name = self.expect_identifier("a constant name")?;
+
+        let body = self.parse_block()?;
+
+        Ok(AstNode::ConstDeclaration {
+            name,
+            body,
+            location,
+        })
+    }
+
+    fn parse_var_declaration(&mut self) -> Result<AstNode, ParserError> {
+        let location = self.previous_location();
+        let name = self.expect_identifier("a variable name")?;
+
+        Ok(AstNode::VarDeclaration { name, location })
+    }
+
+    fn parse_node(&mut self) -> Result<AstNode, ParserError> {
+        let token = match self.peek(0) {
+            Some(token) if !matches!(token.kind, TokenKind::Eof | TokenKind::Error(_)) => {
+                token.clone()
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+        self.advance();
+
+        let location = token.location;
+        match token.kind {
+            TokenKind::Integer(i) => Ok(AstNode::IntegerLiteral(i, location)),
+            TokenKind::Float(f) => Ok(AstNode::FloatLiteral(f, location)),
+            TokenKind::String(s) => Ok(AstNode::StringLiteral(s, location)),
+            TokenKind::True => Ok(AstNode::BooleanLiteral(true, location)),
+            TokenKind::False => Ok(AstNode::BooleanLiteral(false, location)),
+            TokenKind::Identifier(name) => self.parse_identifier(name, location),
+            TokenKind::Fun => self.parse_function_definition(),
+            TokenKind::While => self.parse_while_expression(),
+            TokenKind::For => self.parse_for_expression(),
+            TokenKind::Try => self.parse_try_expression(),
+            TokenKind::If => self.parse_if_expression(),
+            TokenKind::Call => self.parse_function_call(),
+            TokenKind::Let => self.parse_let_declaration(),
+            TokenKind::OpenBracket => self.parse_quotation(),
+            TokenKind::Struct => self.parse_struct_declaration(),
+            TokenKind::Dot => self.parse_field_access(),
+            TokenKind::Enum => self.parse_enum_declaration(),
+            TokenKind::Match => self.parse_match_expression(),
+            TokenKind::Import => self.parse_import(),
+            TokenKind::Const => self.parse_const_declaration(),
+            TokenKind::Var => self.parse_var_declaration(),
+            kind => match self.get_function_name(&kind) {
+                Some(name) => Ok(AstNode::FunctionCall { name, location }),
+                None => Err(ParserError::UnexpectedToken {
+                    expected: "an expression".to_string(),
+                    found: kind,
+                    location,
+                }),
             },
         }
     }
 
-    pub fn parse(&mut self) -> Vec<AstNode> {
+    pub fn parse(&mut self) -> Result<Vec<AstNode>, ParserError> {
         let mut ast = Vec::new();
 
         while !self.is_at_end() {
-            let node = self.parse_node();
+            let node = self.parse_node()?;
             ast.push(node);
         }
 
-        ast
+        Ok(ast)
     }
 }
@@ -0,0 +1,54 @@
+/// Limits on the resources a program may use, for running untrusted scripts.
+/// Every limit is off unless set.
+///
+/// ```
+/// use stack_based_lang::InterpreterConfig;
+///
+/// let config = InterpreterConfig::new()
+///     .max_stack_depth(1024)
+///     .max_call_depth(256)
+///     .max_string_bytes(1 << 20)
+///     .max_collection_size(10_000);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InterpreterConfig {
+    pub(super) max_stack_depth: Option<usize>,
+    pub(super) max_call_depth: Option<usize>,
+    pub(super) max_string_bytes: Option<usize>,
+    pub(super) max_collection_size: Option<usize>,
+}
+
+impl InterpreterConfig {
+    pub fn new() -> Self {
+        Self {
+            max_stack_depth: None,
+            max_call_depth: None,
+            max_string_bytes: None,
+            max_collection_size: None,
+        }
+    }
+
+    /// The most values the stack may hold.
+    pub fn max_stack_depth(mut self, depth: usize) -> Self {
+        self.max_stack_depth = Some(depth);
+        self
+    }
+
+    /// The most function or closure calls that may be active at once.
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = Some(depth);
+        self
+    }
+
+    /// The most bytes the strings created while running may add up to.
+    pub fn max_string_bytes(mut self, bytes: usize) -> Self {
+        self.max_string_bytes = Some(bytes);
+        self
+    }
+
+    /// The most elements a list may hold.
+    pub fn max_collection_size(mut self, size: usize) -> Self {
+        self.max_collection_size = Some(size);
+        self
+    }
+}
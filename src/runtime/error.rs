@@ -1,22 +1,229 @@
 use crate::token::location::Location;
 
+/// A call active when an error stopped the program: the function and where
+/// it was when the error was raised, or where it made the next call.
+#[derive(Debug, Clone)]
+pub struct BacktraceFrame {
+    /// The name of the function, or `closure@N` for the closure at block `N`.
+    pub function: String,
+    pub location: Location,
+}
+
 #[derive(Debug, Clone)]
 pub enum RuntimeError {
     StackUnderflow(Location),
-    InvalidTypes(Location),
+    /// The operands of `operation` have types it doesn't support.
+    InvalidTypes {
+        operation: String,
+        operands: Vec<String>,
+        location: Location,
+    },
+    UnknownField(String, Location),
+    UnmatchedVariant(String, Location),
+    IndexOutOfBounds(i64, usize, Location),
+    Panic(String, Location),
+    UninitializedGlobal(String, Location),
+    DivisionByZero(Location),
+    IntegerOverflow(Location),
+    InvalidInteger(String, Location),
+    Sandboxed(Location),
+    StackOverflow(Location),
+    CallDepthExceeded(Location),
+    StringBytesExceeded(Location),
+    CollectionTooLarge(usize, Location),
+    OutOfFuel(Location),
+    Timeout(Location),
+    UnknownFunction(String),
+    Io {
+        message: String,
+        location: Option<Location>,
+    },
+    Native {
+        name: String,
+        message: String,
+        location: Location,
+    },
+    Thrown {
+        message: String,
+        location: Location,
+    },
+    /// An error no handler caught, with the calls active when it was raised,
+    /// innermost first.
+    Uncaught {
+        error: Box<RuntimeError>,
+        backtrace: Vec<BacktraceFrame>,
+    },
+}
+
+impl RuntimeError {
+    /// The name a caught runtime error reports in the `kind` field of its
+    /// `Error` record.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RuntimeError::StackUnderflow(_) => "StackUnderflow",
+            RuntimeError::InvalidTypes { .. } => "InvalidTypes",
+            RuntimeError::UnknownField(..) => "UnknownField",
+            RuntimeError::UnmatchedVariant(..) => "UnmatchedVariant",
+            RuntimeError::IndexOutOfBounds(..) => "IndexOutOfBounds",
+            RuntimeError::Panic(..) => "Panic",
+            RuntimeError::UninitializedGlobal(..) => "UninitializedGlobal",
+            RuntimeError::DivisionByZero(_) => "DivisionByZero",
+            RuntimeError::IntegerOverflow(_) => "IntegerOverflow",
+            RuntimeError::InvalidInteger(..) => "InvalidInteger",
+            RuntimeError::Sandboxed(_) => "Sandboxed",
+            RuntimeError::StackOverflow(_) => "StackOverflow",
+            RuntimeError::CallDepthExceeded(_) => "CallDepthExceeded",
+            RuntimeError::StringBytesExceeded(_) => "StringBytesExceeded",
+            RuntimeError::CollectionTooLarge(..) => "CollectionTooLarge",
+            RuntimeError::OutOfFuel(_) => "OutOfFuel",
+            RuntimeError::Timeout(_) => "Timeout",
+            RuntimeError::UnknownFunction(_) => "UnknownFunction",
+            RuntimeError::Native { .. } => "NativeError",
+            RuntimeError::Io { .. } => "IoError",
+            RuntimeError::Thrown { .. } => "Thrown",
+            RuntimeError::Uncaught { error, .. } => error.kind(),
+        }
+    }
+
+    /// The error itself, without the backtrace of an uncaught error.
+    pub fn cause(&self) -> &RuntimeError {
+        match self {
+            RuntimeError::Uncaught { error, .. } => error,
+            error => error,
+        }
+    }
+
+    pub fn backtrace(&self) -> &[BacktraceFrame] {
+        match self {
+            RuntimeError::Uncaught { backtrace, .. } => backtrace,
+            _ => &[],
+        }
+    }
+
+    /// Whether the error stopped the program because of the limits set by the
+    /// host rather than a problem in the program. These can't be caught, and
+    /// the program can be resumed after them.
+    pub fn is_interruption(&self) -> bool {
+        matches!(
+            self.cause(),
+            RuntimeError::OutOfFuel(_) | RuntimeError::Timeout(_)
+        )
+    }
+
+    /// Whether the program went past one of the limits of its
+    /// `InterpreterConfig`. These can't be caught, so that a script can't
+    /// carry on once it exceeds them.
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self.cause(),
+            RuntimeError::StackOverflow(_)
+                | RuntimeError::CallDepthExceeded(_)
+                | RuntimeError::StringBytesExceeded(_)
+                | RuntimeError::CollectionTooLarge(..)
+        )
+    }
 }
 
 impl std::fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RuntimeError::StackUnderflow(location) => write!(
+            RuntimeError::StackUnderflow(location) => write!(f, "Stack underflow at {}", location),
+            RuntimeError::InvalidTypes {
+                operation,
+                operands,
+                location,
+            } => {
+                let operands = match operands.split_last() {
+                    Some((last, [])) => last.clone(),
+                    Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+                    None => "values".to_string(),
+                };
+
+                write!(f, "Cannot {} {} at {}", operation, operands, location)
+            }
+            RuntimeError::UnknownField(field, location) => {
+                write!(f, "Record has no field `{}` at {}", field, location)
+            }
+            RuntimeError::UnmatchedVariant(variant, location) => {
+                write!(f, "No case matches variant `{}` at {}", variant, location)
+            }
+            RuntimeError::IndexOutOfBounds(index, length, location) => write!(
+                f,
+                "Index {} is out of bounds for a list of length {} at {}",
+                index, length, location
+            ),
+            RuntimeError::Panic(message, location) => write!(f, "{} at {}", message, location),
+            RuntimeError::DivisionByZero(location) => {
+                write!(f, "Division by zero at {}", location)
+            }
+            RuntimeError::IntegerOverflow(location) => {
+                write!(f, "Integer overflow at {}", location)
+            }
+            RuntimeError::UnknownFunction(name) => write!(f, "Unknown function `{}`", name),
+            RuntimeError::Io {
+                message,
+                location: Some(location),
+            } => write!(f, "I/O error: {} at {}", message, location),
+            RuntimeError::Io {
+                message,
+                location: None,
+            } => write!(f, "I/O error: {}", message),
+            RuntimeError::Native {
+                name,
+                message,
+                location,
+            } => write!(
                 f,
-                "Stack underflow at {}:{}",
-                location.line, location.column
+                "Native word `{}` failed: {} at {}",
+                name, message, location
             ),
-            RuntimeError::InvalidTypes(location) => {
-                write!(f, "Invalid types at {}:{}", location.line, location.column)
+            RuntimeError::InvalidInteger(input, location) => {
+                write!(f, "Expected an integer, read {:?} at {}", input, location)
+            }
+            RuntimeError::Sandboxed(location) => {
+                write!(
+                    f,
+                    "File system access is disabled in the sandbox at {}",
+                    location
+                )
+            }
+            RuntimeError::StackOverflow(location) => {
+                write!(f, "The stack holds too many values at {}", location)
             }
+            RuntimeError::CallDepthExceeded(location) => {
+                write!(f, "Too many nested calls at {}", location)
+            }
+            RuntimeError::StringBytesExceeded(location) => {
+                write!(f, "Strings use too much memory at {}", location)
+            }
+            RuntimeError::CollectionTooLarge(size, location) => {
+                write!(
+                    f,
+                    "A list of {} elements is too large at {}",
+                    size, location
+                )
+            }
+            RuntimeError::OutOfFuel(location) => write!(f, "Ran out of fuel at {}", location),
+            RuntimeError::Timeout(location) => write!(f, "Timed out at {}", location),
+            RuntimeError::Thrown {
+                message, location, ..
+            } => write!(f, "Uncaught exception {} at {}", message, location),
+            RuntimeError::Uncaught { error, backtrace } => {
+                write!(f, "{}", error)?;
+
+                for frame in backtrace {
+                    write!(f, "\n    in `{}` at {}", frame.function, frame.location)?;
+                }
+
+                Ok(())
+            }
+            RuntimeError::UninitializedGlobal(name, location) => write!(
+                f,
+                "Global variable `{}` is read before being assigned at {}",
+                name, location
+            ),
         }
     }
 }
+
+impl std::error::Error for RuntimeError {}
@@ -0,0 +1,16 @@
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub return_block: usize,
+    pub return_ip: usize,
+    pub locals_base: usize,
+}
+
+impl Frame {
+    pub fn new(return_block: usize, return_ip: usize, locals_base: usize) -> Self {
+        Self {
+            return_block,
+            return_ip,
+            locals_base,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+/// An active `try`: where to resume when an error is raised inside its body,
+/// and how far to unwind the interpreter state before doing so.
+#[derive(Debug, Clone)]
+pub struct Handler {
+    pub block: usize,
+    pub ip: usize,
+    pub frames: usize,
+    pub stack: usize,
+    pub locals: usize,
+}
+
+impl Handler {
+    pub fn new(block: usize, ip: usize, frames: usize, stack: usize, locals: usize) -> Self {
+        Self {
+            block,
+            ip,
+            frames,
+            stack,
+            locals,
+        }
+    }
+}
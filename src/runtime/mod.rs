@@ -1,559 +1,1703 @@
-use crate::compiler::{
-    instruction::{Instruction, InstructionKind},
-    program::Program,
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    time::Instant,
+};
+
+use crate::{
+    compiler::{
+        instruction::{Instruction, InstructionKind},
+        program::Program,
+    },
+    token::location::Location,
 };
 
 use self::{
-    error::RuntimeError,
-    value::{Str, Value},
+    config::InterpreterConfig,
+    error::{BacktraceFrame, RuntimeError},
+    frame::Frame,
+    handler::Handler,
+    profile::Profiler,
+    trace::Tracer,
+    value::{Closure, Record, Str, Value, Variant},
 };
 
-mod error;
+pub mod config;
+pub mod error;
+pub mod frame;
+mod handler;
+pub mod native;
+pub mod profile;
+pub mod trace;
 pub mod value;
 
-#[derive(Debug)]
 pub struct Interpreter {
     stack: Vec<Value>,
+    locals: Vec<Value>,
+    frames: Vec<Frame>,
+    handlers: Vec<Handler>,
+    /// Values of the `var` globals, `None` until they are first stored to.
+    globals: Vec<Option<Value>>,
+    /// The value of the `Thrown` error being raised, passed to the handler
+    /// that catches it.
+    thrown: Option<Value>,
     program: Program,
     bp: usize,
     ip: usize,
 
-    brp: usize,
-    irp: usize,
+    /// Where `print` and `???` write, standard output unless the host
+    /// provides another writer.
+    output: Box<dyn Write>,
+    /// Where input words read from, standard input unless the host provides
+    /// another reader.
+    input: Box<dyn BufRead>,
+    /// Whether the file system words are disabled.
+    sandbox: bool,
+    /// The command-line arguments pushed by `args`.
+    args: Vec<String>,
+
+    /// How many more instructions may run, unlimited when `None`.
+    fuel: Option<u64>,
+    /// When to stop running, never when `None`.
+    deadline: Option<Instant>,
+
+    config: InterpreterConfig,
+    /// Bytes of the strings created while running, checked against
+    /// `InterpreterConfig::max_string_bytes`.
+    string_bytes: usize,
+
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+}
+
+impl std::fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("stack", &self.stack)
+            .field("locals", &self.locals)
+            .field("frames", &self.frames)
+            .field("handlers", &self.handlers)
+            .field("globals", &self.globals)
+            .field("thrown", &self.thrown)
+            .field("bp", &self.bp)
+            .field("ip", &self.ip)
+            .field("sandbox", &self.sandbox)
+            .field("args", &self.args)
+            .field("fuel", &self.fuel)
+            .field("deadline", &self.deadline)
+            .field("config", &self.config)
+            .field("string_bytes", &self.string_bytes)
+            .field("tracer", &self.tracer)
+            .field("profiler", &self.profiler)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Interpreter {
     pub fn new(program: Program) -> Self {
-        let bp = &program.functions.get("main").map_or(0, |i| *i);
+        Self::with_config(program, InterpreterConfig::new())
+    }
+
+    /// An interpreter for `program` enforcing the limits in `config`.
+    pub fn with_config(program: Program, config: InterpreterConfig) -> Self {
+        let bp = program.entry_point;
+        let globals = vec![None; program.globals.len()];
 
         Self {
             stack: Vec::new(),
+            locals: Vec::new(),
+            frames: Vec::new(),
+            handlers: Vec::new(),
+            globals,
+            thrown: None,
             program,
-            bp: *bp,
+            bp,
             ip: 0,
 
-            brp: 0,
-            irp: 0,
+            output: Box::new(io::stdout()),
+            input: Box::new(io::BufReader::new(io::stdin())),
+            sandbox: false,
+            args: Vec::new(),
+
+            fuel: None,
+            deadline: None,
+
+            config,
+            string_bytes: 0,
+
+            tracer: None,
+            profiler: None,
         }
     }
 
+    /// Logs the instructions the program runs to `tracer`.
+    pub fn with_tracer(mut self, mut tracer: Tracer) -> Self {
+        tracer.set_labels(self.program.block_labels());
+
+        self.tracer = Some(tracer);
+        self
+    }
+
+    /// Measures the instructions the program runs with `profiler`.
+    pub fn with_profiler(mut self, mut profiler: Profiler) -> Self {
+        profiler.labels = self.program.block_labels();
+
+        self.profiler = Some(profiler);
+        self
+    }
+
+    /// The profiler set with `with_profiler`, holding what ran so far.
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Limits the program to running `fuel` instructions.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Stops the program once `deadline` has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// The number of instructions the program may still run, if limited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Adds `fuel` instructions to the budget, so a program that ran out of
+    /// fuel can be resumed.
+    pub fn refuel(&mut self, fuel: u64) {
+        self.fuel = Some(self.fuel.unwrap_or(0).saturating_add(fuel));
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Sets the command-line arguments the program receives through `args`.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Disables the file system words, which then raise a runtime error.
+    pub fn with_sandbox(mut self, sandbox: bool) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Sends the output of the program to `output` instead of standard output.
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Reads the input of the program from `input` instead of standard input.
+    pub fn with_input(mut self, input: impl BufRead + 'static) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+
+    /// The writer the program prints to, for native words producing output.
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut *self.output
+    }
+
+    /// The reader the program reads from, for native words consuming input.
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        &mut *self.input
+    }
+
     fn current_instruction(&self) -> &Instruction {
         &self.program.blocks[self.bp].instructions[self.ip]
     }
 
+    /// Renders a value the way `print` shows it, resolving strings and record
+    /// field names through the program.
+    pub fn format_value(&self, value: &Value) -> String {
+        match value {
+            Value::String(Str { string_index, .. }) => self.program.strings[*string_index].clone(),
+            Value::Record(record) => {
+                let definition = &self.program.structs[record.type_id];
+                let fields = definition
+                    .fields
+                    .iter()
+                    .zip(record.fields.iter())
+                    .map(|(name, value)| format!("{}: {}", name, self.format_nested(value)))
+                    .collect::<Vec<_>>();
+
+                format!("{} {{ {} }}", definition.name, fields.join(", "))
+            }
+            Value::Variant(variant) => {
+                let definition = &self.program.enums[variant.type_id].variants[variant.tag];
+
+                if variant.fields.is_empty() {
+                    return definition.name.clone();
+                }
+
+                let fields = variant
+                    .fields
+                    .iter()
+                    .map(|value| self.format_nested(value))
+                    .collect::<Vec<_>>();
+
+                format!("{}({})", definition.name, fields.join(", "))
+            }
+            Value::List(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.format_nested(value))
+                    .collect::<Vec<_>>();
+
+                format!("[{}]", values.join(", "))
+            }
+            _ => value.to_string(),
+        }
+    }
+
+    /// Like `format_value`, but quotes strings so they stand out inside a record or list.
+    fn format_nested(&self, value: &Value) -> String {
+        match value {
+            Value::String(_) => format!("{:?}", self.format_value(value)),
+            _ => self.format_value(value),
+        }
+    }
+
+    /// The name of the type of `value` in error messages: the declared name
+    /// for records and variants.
+    fn type_name(&self, value: &Value) -> String {
+        let name = match value {
+            Value::I64(_) => "Int",
+            Value::F64(_) => "Float",
+            Value::Bool(_) => "Bool",
+            Value::String(_) | Value::RawString(_) => "String",
+            Value::Closure(_) => "Closure",
+            Value::Record(record) => &self.program.structs[record.type_id].name,
+            Value::Variant(variant) => &self.program.enums[variant.type_id].name,
+            Value::List(_) => "List",
+        };
+
+        name.to_string()
+    }
+
+    /// The error for `instruction` getting operands of the wrong types.
+    fn invalid_types(&self, instruction: &Instruction, operands: &[&Value]) -> RuntimeError {
+        let operation = match instruction.kind {
+            InstructionKind::Add => "add",
+            InstructionKind::Sub => "subtract",
+            InstructionKind::Mul => "multiply",
+            InstructionKind::Div => "divide",
+            InstructionKind::Mod => "take the remainder of",
+            InstructionKind::Pow => "raise",
+            InstructionKind::Neg | InstructionKind::Not => "negate",
+            InstructionKind::And | InstructionKind::Or => "combine",
+            InstructionKind::Equals | InstructionKind::NotEquals => "compare the equality of",
+            InstructionKind::LessThan
+            | InstructionKind::LessThanEquals
+            | InstructionKind::GreaterThan
+            | InstructionKind::GreaterThanEquals => "compare",
+            InstructionKind::JumpIfFalse(_) | InstructionKind::JumpIfTrue(_) => "branch on",
+            InstructionKind::CallClosure => "apply",
+            InstructionKind::Match { .. } => "match on",
+            InstructionKind::GetField(_) | InstructionKind::SetField(_) => "access a field of",
+            InstructionKind::ListPush => "push onto",
+            InstructionKind::ListGet | InstructionKind::ListSet => "index into",
+            InstructionKind::Length => "take the length of",
+            InstructionKind::FileRead
+            | InstructionKind::FileWrite
+            | InstructionKind::FileAppend
+            | InstructionKind::FileExists
+            | InstructionKind::FileLines
+            | InstructionKind::DirList => "use as a path",
+            InstructionKind::EnvGet => "use as a variable name",
+            _ => "use",
+        };
+
+        RuntimeError::InvalidTypes {
+            operation: operation.to_string(),
+            operands: operands.iter().map(|value| self.type_name(value)).collect(),
+            location: instruction.location.clone(),
+        }
+    }
+
+    fn pop_operand(&mut self, instruction: &Instruction) -> Result<Value, RuntimeError> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+        }
+    }
+
+    fn pop_list(&mut self, instruction: &Instruction) -> Result<Vec<Value>, RuntimeError> {
+        match self.pop_operand(instruction)? {
+            Value::List(values) => Ok(values),
+            value => Err(self.invalid_types(instruction, &[&value])),
+        }
+    }
+
+    fn pop_string(&mut self, instruction: &Instruction) -> Result<String, RuntimeError> {
+        match self.pop_operand(instruction)? {
+            Value::String(string) => Ok(self.program.strings[string.string_index].clone()),
+            Value::RawString(string) => Ok(string),
+            value => Err(self.invalid_types(instruction, &[&value])),
+        }
+    }
+
+    /// Pops the path operand of a file system word, unless they are disabled.
+    fn pop_path(&mut self, instruction: &Instruction) -> Result<String, RuntimeError> {
+        if self.sandbox {
+            return Err(RuntimeError::Sandboxed(instruction.location.clone()));
+        }
+
+        self.pop_string(instruction)
+    }
+
+    fn list_index(
+        &self,
+        index: Value,
+        length: usize,
+        instruction: &Instruction,
+    ) -> Result<usize, RuntimeError> {
+        match index {
+            Value::I64(index) if index >= 0 && (index as usize) < length => Ok(index as usize),
+            Value::I64(index) => Err(RuntimeError::IndexOutOfBounds(
+                index,
+                length,
+                instruction.location.clone(),
+            )),
+            index => Err(RuntimeError::InvalidTypes {
+                operation: "index a list with".to_string(),
+                operands: vec![self.type_name(&index)],
+                location: instruction.location.clone(),
+            }),
+        }
+    }
+
+    /// Interns `string` in the program, returning a value that can be pushed
+    /// onto the stack.
+    pub fn new_string(&mut self, string: &str) -> Value {
+        let count = self.program.strings.len();
+        let index = self.program.add_string(string);
+
+        if self.program.strings.len() > count {
+            self.string_bytes += string.len();
+        }
+
+        Value::String(Str::new(index, string.len()))
+    }
+
+    /// The text of a string value, or `None` when `value` isn't a string.
+    pub fn string<'a>(&'a self, value: &'a Value) -> Option<&'a str> {
+        match value {
+            Value::String(string) => Some(&self.program.strings[string.string_index]),
+            Value::RawString(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Checks the limits of the configuration after running the instruction
+    /// at `location`.
+    fn check_limits(&self, location: &Location) -> Result<(), RuntimeError> {
+        let location = || location.clone();
+        let exceeds = |value: usize, limit: Option<usize>| limit.is_some_and(|limit| value > limit);
+
+        if exceeds(self.stack.len(), self.config.max_stack_depth) {
+            return Err(RuntimeError::StackOverflow(location()));
+        }
+
+        if exceeds(self.frames.len(), self.config.max_call_depth) {
+            return Err(RuntimeError::CallDepthExceeded(location()));
+        }
+
+        if exceeds(self.string_bytes, self.config.max_string_bytes) {
+            return Err(RuntimeError::StringBytesExceeded(location()));
+        }
+
+        Ok(())
+    }
+
+    /// Pushes a list built by `instruction`, unless it has more elements than
+    /// `InterpreterConfig::max_collection_size` allows.
+    fn push_list(
+        &mut self,
+        values: Vec<Value>,
+        instruction: &Instruction,
+    ) -> Result<(), RuntimeError> {
+        self.check_collection(&values, instruction)?;
+
+        self.stack.push(Value::List(values));
+        Ok(())
+    }
+
+    fn check_collection(
+        &self,
+        values: &[Value],
+        instruction: &Instruction,
+    ) -> Result<(), RuntimeError> {
+        match self.config.max_collection_size {
+            Some(limit) if values.len() > limit => Err(RuntimeError::CollectionTooLarge(
+                values.len(),
+                instruction.location.clone(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&mut self, text: &str, instruction: &Instruction) -> Result<(), RuntimeError> {
+        self.output
+            .write_all(text.as_bytes())
+            .map_err(|error| RuntimeError::Io {
+                message: error.to_string(),
+                location: Some(instruction.location.clone()),
+            })
+    }
+
+    /// Reads the next line of input without its line ending, or `None` at the
+    /// end of the input.
+    fn read_line(&mut self, instruction: &Instruction) -> Result<Option<String>, RuntimeError> {
+        let mut line = String::new();
+        let read = self
+            .input
+            .read_line(&mut line)
+            .map_err(|error| RuntimeError::Io {
+                message: error.to_string(),
+                location: Some(instruction.location.clone()),
+            })?;
+
+        if read == 0 {
+            return Ok(None);
+        }
+
+        if line.ends_with('\n') {
+            line.pop();
+
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+
+        Ok(Some(line))
+    }
+
+    fn record_field(&self, record: &Record, field: &str) -> Option<usize> {
+        self.program.structs[record.type_id].field_index(field)
+    }
+
+    fn locals_base(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.locals_base)
+    }
+
+    fn push_frame(&mut self, block: usize) {
+        self.frames
+            .push(Frame::new(self.bp, self.ip + 1, self.locals.len()));
+
+        self.bp = block;
+        self.ip = 0;
+    }
+
+    pub fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    pub fn pop(&mut self) -> Option<Value> {
+        self.stack.pop()
+    }
+
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    /// Runs the program from its entry point, returning the value left on top
+    /// of the stack.
+    ///
+    /// When it stops because of an `OutOfFuel` or `Timeout` error, the
+    /// interpreter is left at the instruction it didn't run, and calling
+    /// `interpret` again (after refueling or moving the deadline) continues
+    /// from there.
     pub fn interpret(&mut self) -> Result<Value, RuntimeError> {
+        let result = self.execute(0);
+        self.flush(result)?;
+
+        let result = match self.stack.pop() {
+            Some(value) => match value {
+                Value::String(string) => {
+                    let string = self.program.strings[string.string_index].clone();
+                    Value::RawString(string)
+                }
+                _ => value,
+            },
+            None => Value::I64(0),
+        };
+
+        Ok(result)
+    }
+
+    /// Calls the function `name` on the current stack. Its arguments are taken
+    /// from the stack and its results are left there. Unlike `interpret`, a
+    /// call stopped by the fuel or time limits can't be resumed.
+    pub fn call(&mut self, name: &str) -> Result<(), RuntimeError> {
+        let block = match self.program.functions.get(name) {
+            Some(&block) => block,
+            None => return Err(RuntimeError::UnknownFunction(name.to_string())),
+        };
+
+        let (bp, ip) = (self.bp, self.ip);
+        let (frames, locals, handlers) =
+            (self.frames.len(), self.locals.len(), self.handlers.len());
+
+        // Returning to a block past the end of the program stops `run`.
+        self.frames.push(Frame::new(usize::MAX, 0, locals));
+        self.bp = block;
+        self.ip = 0;
+
+        let result = self.execute(handlers);
+        let result = self.flush(result);
+
+        self.frames.truncate(frames);
+        self.locals.truncate(locals);
+        self.handlers.truncate(handlers);
+        self.bp = bp;
+        self.ip = ip;
+
+        result
+    }
+
+    /// Flushes the output and the trace once the program stops, whether it
+    /// finished or failed. An error raised by the program takes precedence
+    /// over one raised while flushing.
+    fn flush<T>(&mut self, result: Result<T, RuntimeError>) -> Result<T, RuntimeError> {
+        let traced = match &mut self.tracer {
+            Some(tracer) => tracer.output.flush(),
+            None => Ok(()),
+        };
+        let flushed = self
+            .output
+            .flush()
+            .and(traced)
+            .map_err(|error| RuntimeError::Io {
+                message: error.to_string(),
+                location: None,
+            });
+
+        let value = result?;
+        flushed.map(|()| value)
+    }
+
+    /// Runs a single instruction, resuming at a `try` handler on errors like
+    /// `interpret` does. Returns whether the program goes on. The output is
+    /// flushed once the program stops, not after every instruction.
+    pub fn step(&mut self) -> Result<bool, RuntimeError> {
+        let result = match self.execute_instruction() {
+            Ok(running) => Ok(running),
+            Err(error) => self.recover(error, 0).map(|()| true),
+        };
+
+        match result {
+            Ok(true) => Ok(true),
+            result => self.flush(result),
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// The block and index of the next instruction to run.
+    pub fn position(&self) -> (usize, usize) {
+        (self.bp, self.ip)
+    }
+
+    /// The location of the next instruction to run, if the program isn't done.
+    pub fn location(&self) -> Option<&Location> {
+        self.program
+            .blocks
+            .get(self.bp)
+            .and_then(|block| block.instructions.get(self.ip))
+            .map(|instruction| &instruction.location)
+    }
+
+    /// The number of calls active, not counting the entry point.
+    pub fn call_depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The calls active at the current instruction, innermost first: the
+    /// block of each function and where it is, or where it made the next
+    /// call. The list ends at a host `call`, which returns outside of the
+    /// program.
+    pub(crate) fn call_stack(&self) -> Vec<(usize, Option<Location>)> {
+        let mut calls = vec![(self.bp, self.location().cloned())];
+
+        for frame in self.frames.iter().rev() {
+            let call = self
+                .program
+                .blocks
+                .get(frame.return_block)
+                .and_then(|block| block.instructions.get(frame.return_ip.checked_sub(1)?));
+
+            match call {
+                Some(call) => calls.push((frame.return_block, Some(call.location.clone()))),
+                None => break,
+            }
+        }
+
+        calls
+    }
+
+    /// Runs from the current instruction, resuming at the innermost `try`
+    /// handler whenever an error is raised inside its body. Handlers below
+    /// `base` belong to an outer run and are left alone.
+    fn execute(&mut self, base: usize) -> Result<(), RuntimeError> {
         loop {
-            if self.bp >= self.program.blocks.len()
-                || self.ip >= self.program.blocks[self.bp].instructions.len()
-            {
-                break;
+            match self.run() {
+                Ok(()) => return Ok(()),
+                Err(error) => self.recover(error, base)?,
             }
+        }
+    }
 
-            let instruction = self.current_instruction().clone();
+    /// Unwinds to the innermost handler above `base` and passes it `error`,
+    /// or gives the error back with its backtrace when there is none.
+    fn recover(&mut self, error: RuntimeError, base: usize) -> Result<(), RuntimeError> {
+        // Running out of fuel or time, or going past a limit, stops the
+        // program regardless of any handler. After an interruption, its state
+        // is kept so that it can be resumed.
+        if error.is_interruption() || error.is_limit() || self.handlers.len() <= base {
+            return Err(self.uncaught(error));
+        }
 
-            match instruction.kind {
-                InstructionKind::Halt => break,
-                InstructionKind::NoOp => {}
-                InstructionKind::DebugStack => {
-                    println!("Stack: {:#?}", self.stack);
-                    self.ip += 1;
-                }
-                InstructionKind::LoadI64(value) => {
-                    self.stack.push(Value::I64(value));
-                    self.ip += 1;
-                }
-                InstructionKind::LoadF64(value) => {
-                    self.stack.push(Value::F64(value));
-                    self.ip += 1;
-                }
-                InstructionKind::LoadBool(value) => {
-                    self.stack.push(Value::Bool(value));
-                    self.ip += 1;
-                }
-                InstructionKind::LoadConstant(index) => {
-                    let string_length = self.program.strings[index].len();
-                    let value = Value::String(Str::new(index, string_length));
+        let handler = match self.handlers.pop() {
+            Some(handler) => handler,
+            None => return Err(error),
+        };
 
-                    self.stack.push(value);
-                    self.ip += 1;
-                }
-                InstructionKind::Add => {
-                    // TODO: handle types here
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        self.frames.truncate(handler.frames);
+        self.stack.truncate(handler.stack);
+        self.locals.truncate(handler.locals);
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
-
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::I64(left + right),
-                        (Value::F64(left), Value::F64(right)) => Value::F64(left + right),
-                        (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 + right),
-                        (Value::F64(left), Value::I64(right)) => Value::F64(left + right as f64),
-                        (Value::String(left), Value::String(right)) => {
-                            let mut string = self.program.strings[left.string_index].clone();
-                            string.push_str(&self.program.strings[right.string_index]);
-
-                            let index = self.program.add_string(&string);
-                            Value::String(Str::new(index, string.len()))
-                        }
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+        let value = self.error_value(error);
+        self.stack.push(value);
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Sub => {
-                    // TODO: handle types here
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        self.bp = handler.block;
+        self.ip = handler.ip;
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        Ok(())
+    }
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::I64(left - right),
-                        (Value::F64(left), Value::F64(right)) => Value::F64(left - right),
-                        (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 - right),
-                        (Value::F64(left), Value::I64(right)) => Value::F64(left - right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+    /// Attaches to `error` the backtrace of the calls active when it was
+    /// raised, before the error stops the program.
+    fn uncaught(&self, error: RuntimeError) -> RuntimeError {
+        if let RuntimeError::Uncaught { .. } = error {
+            return error;
+        }
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Mul => {
-                    // TODO: handle types here
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        let backtrace = self
+            .call_stack()
+            .into_iter()
+            .filter_map(|(block, location)| {
+                Some(BacktraceFrame {
+                    function: self.program.block_label(block),
+                    location: location?,
+                })
+            })
+            .collect();
+
+        RuntimeError::Uncaught {
+            error: Box::new(error),
+            backtrace,
+        }
+    }
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+    /// The value a handler receives for `error`: the thrown value itself, or
+    /// an `Error` record describing a runtime error.
+    fn error_value(&mut self, error: RuntimeError) -> Value {
+        if let (RuntimeError::Thrown { .. }, Some(value)) = (&error, self.thrown.take()) {
+            return value;
+        }
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::I64(left * right),
-                        (Value::F64(left), Value::F64(right)) => Value::F64(left * right),
-                        (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 * right),
-                        (Value::F64(left), Value::I64(right)) => Value::F64(left * right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+        let type_id = match self.program.struct_index("Error") {
+            Some(type_id) => type_id,
+            None => unreachable!(),
+        };
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Div => {
-                    // TODO: handle types here
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        let fields = vec![
+            self.new_string(error.kind()),
+            self.new_string(&error.to_string()),
+        ];
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        Value::Record(Record::new(type_id, fields))
+    }
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => {
-                            Value::F64(left as f64 / right as f64)
-                        }
-                        (Value::F64(left), Value::F64(right)) => Value::F64(left / right),
-                        (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 / right),
-                        (Value::F64(left), Value::I64(right)) => Value::F64(left / right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        while self.execute_instruction()? {}
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Mod => {
-                    // TODO: handle types here
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        Ok(())
+    }
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+    /// Runs the current instruction, tracing it when a tracer is set.
+    fn execute_instruction(&mut self) -> Result<bool, RuntimeError> {
+        let (bp, ip) = (self.bp, self.ip);
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::I64(left % right),
-                        (Value::F64(left), Value::F64(right)) => Value::F64(left % right),
-                        (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 % right),
-                        (Value::F64(left), Value::I64(right)) => Value::F64(left % right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+        let traced = match &self.tracer {
+            Some(tracer) => tracer.traces(bp) && self.location().is_some(),
+            None => false,
+        };
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Not => {
-                    let value = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        if !traced {
+            return self.profile_instruction();
+        }
 
-                    let result = match value {
-                        Value::Bool(value) => Value::Bool(!value),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+        let before = self.stack_top();
+        let result = self.profile_instruction();
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::And => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        let instruction = &self.program.blocks[bp].instructions[ip];
+        let after = match &result {
+            Ok(_) => self.stack_top(),
+            Err(error) => format!("error: {}", error.kind()),
+        };
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        let line = format!(
+            "[{}] {:>4} {:<24} {:<24} {} -> {}\n",
+            self.tracer
+                .as_ref()
+                .map(|tracer| tracer.labels[bp].as_str())
+                .unwrap_or_default(),
+            ip,
+            instruction.kind.to_string(),
+            instruction.location.to_string(),
+            before,
+            after
+        );
+
+        if let Some(tracer) = &mut self.tracer {
+            if let Err(error) = tracer.output.write_all(line.as_bytes()) {
+                return Err(RuntimeError::Io {
+                    message: error.to_string(),
+                    location: None,
+                });
+            }
+        }
 
-                    let result = match (left, right) {
-                        (Value::Bool(left), Value::Bool(right)) => Value::Bool(left && right),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+        result
+    }
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Or => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+    /// Runs the current instruction, measuring it when a profiler is set.
+    fn profile_instruction(&mut self) -> Result<bool, RuntimeError> {
+        let (bp, ip) = (self.bp, self.ip);
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        if self.profiler.is_none() || self.location().is_none() {
+            return self.dispatch();
+        }
 
-                    let result = match (left, right) {
-                        (Value::Bool(left), Value::Bool(right)) => Value::Bool(left || right),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+        // The blocks of the active calls, outermost first, with the frames of
+        // a host `call` returning outside of the program left out.
+        let mut calls = self
+            .frames
+            .iter()
+            .map(|frame| frame.return_block)
+            .filter(|block| *block < self.program.blocks.len())
+            .collect::<Vec<_>>();
+        calls.push(bp);
+
+        let start = Instant::now();
+        let result = self.dispatch();
+        let time = start.elapsed();
+
+        let location = &self.program.blocks[bp].instructions[ip].location;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(calls, location, time);
+        }
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Equals => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        result
+    }
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+    /// The values at the top of the stack, for traces.
+    fn stack_top(&self) -> String {
+        const SHOWN: usize = 3;
 
-                    let result = right.equals(&left);
+        let start = self.stack.len().saturating_sub(SHOWN);
+        let values = self.stack[start..]
+            .iter()
+            .map(|value| self.format_nested(value))
+            .collect::<Vec<_>>();
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::NotEquals => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        let ellipsis = if start > 0 { "..., " } else { "" };
+        format!("[{}{}]", ellipsis, values.join(", "))
+    }
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+    /// Runs the current instruction, returning whether the program goes on.
+    fn dispatch(&mut self) -> Result<bool, RuntimeError> {
+        if self.bp >= self.program.blocks.len()
+            || self.ip >= self.program.blocks[self.bp].instructions.len()
+        {
+            return Ok(false);
+        }
 
-                    let result = match right.equals(&left) {
-                        Value::Bool(value) => Value::Bool(!value),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+        let instruction = self.current_instruction().clone();
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::LessThan => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+        // The deadline comes first so that timing out doesn't use up fuel.
+        if matches!(self.deadline, Some(deadline) if Instant::now() >= deadline) {
+            return Err(RuntimeError::Timeout(instruction.location));
+        }
+
+        match self.fuel {
+            Some(0) => return Err(RuntimeError::OutOfFuel(instruction.location)),
+            Some(fuel) => self.fuel = Some(fuel - 1),
+            None => {}
+        }
+
+        match instruction.kind {
+            InstructionKind::Halt => return Ok(false),
+            InstructionKind::NoOp => {}
+            InstructionKind::DebugStack => {
+                let stack = format!("Stack: {:#?}\n", self.stack);
+                self.write(&stack, &instruction)?;
+                self.ip += 1;
+            }
+            InstructionKind::LoadI64(value) => {
+                self.stack.push(Value::I64(value));
+                self.ip += 1;
+            }
+            InstructionKind::LoadF64(value) => {
+                self.stack.push(Value::F64(value));
+                self.ip += 1;
+            }
+            InstructionKind::LoadBool(value) => {
+                self.stack.push(Value::Bool(value));
+                self.ip += 1;
+            }
+            InstructionKind::LoadConstant(index) => {
+                let string_length = self.program.strings[index].len();
+                let value = Value::String(Str::new(index, string_length));
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::Add => {
+                // TODO: handle types here
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => match left.checked_add(right) {
+                        Some(result) => Value::I64(result),
                         None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
+                            return Err(RuntimeError::IntegerOverflow(instruction.location.clone()))
                         }
-                    };
+                    },
+                    (Value::F64(left), Value::F64(right)) => Value::F64(left + right),
+                    (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 + right),
+                    (Value::F64(left), Value::I64(right)) => Value::F64(left + right as f64),
+                    (Value::String(left), Value::String(right)) => {
+                        let mut string = self.program.strings[left.string_index].clone();
+                        string.push_str(&self.program.strings[right.string_index]);
+
+                        self.new_string(&string)
+                    }
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::Bool(left < right),
-                        (Value::F64(left), Value::F64(right)) => Value::Bool(left < right),
-                        (Value::I64(left), Value::F64(right)) => Value::Bool((left as f64) < right),
-                        (Value::F64(left), Value::I64(right)) => Value::Bool(left < right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Sub => {
+                // TODO: handle types here
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::LessThanEquals => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => match left.checked_sub(right) {
+                        Some(result) => Value::I64(result),
                         None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
+                            return Err(RuntimeError::IntegerOverflow(instruction.location.clone()))
                         }
-                    };
+                    },
+                    (Value::F64(left), Value::F64(right)) => Value::F64(left - right),
+                    (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 - right),
+                    (Value::F64(left), Value::I64(right)) => Value::F64(left - right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Mul => {
+                // TODO: handle types here
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => match left.checked_mul(right) {
+                        Some(result) => Value::I64(result),
                         None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
+                            return Err(RuntimeError::IntegerOverflow(instruction.location.clone()))
                         }
-                    };
+                    },
+                    (Value::F64(left), Value::F64(right)) => Value::F64(left * right),
+                    (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 * right),
+                    (Value::F64(left), Value::I64(right)) => Value::F64(left * right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Div => {
+                // TODO: handle types here
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::Bool(left <= right),
-                        (Value::F64(left), Value::F64(right)) => Value::Bool(left <= right),
-                        (Value::I64(left), Value::F64(right)) => {
-                            Value::Bool((left as f64) <= right)
-                        }
-                        (Value::F64(left), Value::I64(right)) => Value::Bool(left <= right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    self.stack.push(result);
-                    self.ip += 1;
+                if let Value::I64(0) = right {
+                    return Err(RuntimeError::DivisionByZero(instruction.location.clone()));
                 }
-                InstructionKind::GreaterThan => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => Value::F64(left as f64 / right as f64),
+                    (Value::F64(left), Value::F64(right)) => Value::F64(left / right),
+                    (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 / right),
+                    (Value::F64(left), Value::I64(right)) => Value::F64(left / right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::Bool(left > right),
-                        (Value::F64(left), Value::F64(right)) => Value::Bool(left > right),
-                        (Value::I64(left), Value::F64(right)) => Value::Bool((left as f64) > right),
-                        (Value::F64(left), Value::I64(right)) => Value::Bool(left > right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Mod => {
+                // TODO: handle types here
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    self.stack.push(result);
-                    self.ip += 1;
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                if let Value::I64(0) = right {
+                    return Err(RuntimeError::DivisionByZero(instruction.location.clone()));
                 }
-                InstructionKind::GreaterThanEquals => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => match left.checked_rem(right) {
+                        Some(result) => Value::I64(result),
                         None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
+                            return Err(RuntimeError::IntegerOverflow(instruction.location.clone()))
                         }
-                    };
+                    },
+                    (Value::F64(left), Value::F64(right)) => Value::F64(left % right),
+                    (Value::I64(left), Value::F64(right)) => Value::F64(left as f64 % right),
+                    (Value::F64(left), Value::I64(right)) => Value::F64(left % right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Not => {
+                let value = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    let result = match (left, right) {
-                        (Value::I64(left), Value::I64(right)) => Value::Bool(left >= right),
-                        (Value::F64(left), Value::F64(right)) => Value::Bool(left >= right),
-                        (Value::I64(left), Value::F64(right)) => {
-                            Value::Bool((left as f64) >= right)
-                        }
-                        (Value::F64(left), Value::I64(right)) => Value::Bool(left >= right as f64),
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+                let result = match value {
+                    Value::Bool(value) => Value::Bool(!value),
+                    value => return Err(self.invalid_types(&instruction, &[&value])),
+                };
 
-                    self.stack.push(result);
-                    self.ip += 1;
-                }
-                InstructionKind::Call(index) => {
-                    self.brp = self.bp;
-                    self.irp = self.ip + 1;
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::And => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    self.bp = index;
-                    self.ip = 0;
+                let result = match (left, right) {
+                    (Value::Bool(left), Value::Bool(right)) => Value::Bool(left && right),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Or => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match (left, right) {
+                    (Value::Bool(left), Value::Bool(right)) => Value::Bool(left || right),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Equals => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = right.equals(&left);
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::NotEquals => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match right.equals(&left) {
+                    Value::Bool(value) => Value::Bool(!value),
+                    _ => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::LessThan => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => Value::Bool(left < right),
+                    (Value::F64(left), Value::F64(right)) => Value::Bool(left < right),
+                    (Value::I64(left), Value::F64(right)) => Value::Bool((left as f64) < right),
+                    (Value::F64(left), Value::I64(right)) => Value::Bool(left < right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::LessThanEquals => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => Value::Bool(left <= right),
+                    (Value::F64(left), Value::F64(right)) => Value::Bool(left <= right),
+                    (Value::I64(left), Value::F64(right)) => Value::Bool((left as f64) <= right),
+                    (Value::F64(left), Value::I64(right)) => Value::Bool(left <= right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::GreaterThan => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => Value::Bool(left > right),
+                    (Value::F64(left), Value::F64(right)) => Value::Bool(left > right),
+                    (Value::I64(left), Value::F64(right)) => Value::Bool((left as f64) > right),
+                    (Value::F64(left), Value::I64(right)) => Value::Bool(left > right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::GreaterThanEquals => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let result = match (left, right) {
+                    (Value::I64(left), Value::I64(right)) => Value::Bool(left >= right),
+                    (Value::F64(left), Value::F64(right)) => Value::Bool(left >= right),
+                    (Value::I64(left), Value::F64(right)) => Value::Bool((left as f64) >= right),
+                    (Value::F64(left), Value::I64(right)) => Value::Bool(left >= right as f64),
+                    (left, right) => return Err(self.invalid_types(&instruction, &[&left, &right])),
+                };
+
+                self.stack.push(result);
+                self.ip += 1;
+            }
+            InstructionKind::Call(index) => self.push_frame(index),
+            InstructionKind::CallNative(index) => {
+                let native = self.program.natives.get(index).clone();
+
+                if self.stack.len() < native.inputs {
+                    return Err(RuntimeError::StackUnderflow(instruction.location.clone()));
                 }
-                InstructionKind::JumpIfFalse(index) => {
-                    let value = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
 
-                    let result = match value {
-                        Value::Bool(value) => value,
-                        _ => return Err(RuntimeError::InvalidTypes(instruction.location.clone())),
-                    };
+                let arguments = self.stack.split_off(self.stack.len() - native.inputs);
+                let results = match (native.function)(self, arguments) {
+                    Ok(results) if results.len() == native.outputs => results,
+                    Ok(results) => {
+                        return Err(RuntimeError::Native {
+                            name: native.name,
+                            message: format!(
+                                "returned {} value(s) instead of {}",
+                                results.len(),
+                                native.outputs
+                            ),
+                            location: instruction.location.clone(),
+                        })
+                    }
+                    Err(message) => {
+                        return Err(RuntimeError::Native {
+                            name: native.name,
+                            message,
+                            location: instruction.location.clone(),
+                        })
+                    }
+                };
 
-                    if result {
-                        self.ip += 1;
-                    } else {
-                        self.ip = index;
+                for result in &results {
+                    if let Value::List(values) = result {
+                        self.check_collection(values, &instruction)?;
                     }
                 }
-                InstructionKind::Jump(index) => self.ip = index,
-                InstructionKind::Return => {
-                    self.bp = self.brp;
-                    self.ip = self.irp;
+
+                self.stack.extend(results);
+                self.ip += 1;
+            }
+            InstructionKind::CallClosure => {
+                let closure = match self.stack.pop() {
+                    Some(Value::Closure(closure)) => closure,
+                    Some(value) => return Err(self.invalid_types(&instruction, &[&value])),
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                self.push_frame(closure.block);
+                self.locals.extend(closure.captures);
+            }
+            InstructionKind::Bind(count) => {
+                if self.stack.len() < count {
+                    return Err(RuntimeError::StackUnderflow(instruction.location.clone()));
                 }
-                InstructionKind::Dup => {
-                    let value = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
 
-                    self.stack.push(value.clone());
-                    self.stack.push(value);
+                let values = self.stack.split_off(self.stack.len() - count);
+                self.locals.extend(values);
+
+                self.ip += 1;
+            }
+            InstructionKind::Unbind(count) => {
+                self.locals.truncate(self.locals.len() - count);
+                self.ip += 1;
+            }
+            InstructionKind::GetLocal(slot) => {
+                let value = self.locals[self.locals_base() + slot].clone();
 
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::SetLocal(slot) => {
+                let value = self.pop_operand(&instruction)?;
+                let base = self.locals_base();
+
+                self.locals[base + slot] = value;
+                self.ip += 1;
+            }
+            InstructionKind::LoadGlobal(index) => match &self.globals[index] {
+                Some(value) => {
+                    self.stack.push(value.clone());
                     self.ip += 1;
                 }
-                InstructionKind::Drop => match self.stack.pop() {
-                    Some(_) => self.ip += 1,
-                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
-                },
-                InstructionKind::Swap => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+                None => {
+                    return Err(RuntimeError::UninitializedGlobal(
+                        self.program.globals[index].clone(),
+                        instruction.location.clone(),
+                    ))
+                }
+            },
+            InstructionKind::StoreGlobal(index) => {
+                let value = self.pop_operand(&instruction)?;
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+                self.globals[index] = Some(value);
+                self.ip += 1;
+            }
+            InstructionKind::MakeClosure { block, captures } => {
+                let base = self.locals_base();
+                let captures = captures
+                    .iter()
+                    .map(|slot| self.locals[base + slot].clone())
+                    .collect();
+
+                self.stack
+                    .push(Value::Closure(Closure::new(block, captures)));
+                self.ip += 1;
+            }
+            InstructionKind::JumpIfFalse(index) => {
+                let value = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
 
-                    self.stack.push(right);
-                    self.stack.push(left);
+                let result = match value {
+                    Value::Bool(value) => value,
+                    value => return Err(self.invalid_types(&instruction, &[&value])),
+                };
 
+                if result {
                     self.ip += 1;
+                } else {
+                    self.ip = index;
                 }
-                InstructionKind::Over => {
-                    let right = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
+            }
+            InstructionKind::Jump(index) => self.ip = index,
+            InstructionKind::Return => match self.frames.pop() {
+                Some(frame) => {
+                    self.locals.truncate(frame.locals_base);
 
-                    let left = match self.stack.pop() {
-                        Some(value) => value,
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
+                    self.bp = frame.return_block;
+                    self.ip = frame.return_ip;
+                }
+                None => return Ok(false),
+            },
+            InstructionKind::Dup => {
+                let value = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                self.stack.push(value.clone());
+                self.stack.push(value);
+
+                self.ip += 1;
+            }
+            InstructionKind::Drop => match self.stack.pop() {
+                Some(_) => self.ip += 1,
+                None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+            },
+            InstructionKind::Swap => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                self.stack.push(right);
+                self.stack.push(left);
+
+                self.ip += 1;
+            }
+            InstructionKind::Over => {
+                let right = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let left = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                self.stack.push(left.clone());
+                self.stack.push(right);
+                self.stack.push(left);
+
+                self.ip += 1;
+            }
+            InstructionKind::Print => {
+                let value = match self.stack.pop() {
+                    Some(value) => self.format_value(&value),
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                self.write(&value, &instruction)?;
+                self.ip += 1;
+            }
+            InstructionKind::ListNew => {
+                self.stack.push(Value::List(Vec::new()));
+                self.ip += 1;
+            }
+            InstructionKind::ListPush => {
+                let value = self.pop_operand(&instruction)?;
+                let mut values = self.pop_list(&instruction)?;
+
+                values.push(value);
+                self.push_list(values, &instruction)?;
+                self.ip += 1;
+            }
+            InstructionKind::ListGet => {
+                let index = self.pop_operand(&instruction)?;
+                let mut values = self.pop_list(&instruction)?;
+                let index = self.list_index(index, values.len(), &instruction)?;
+
+                self.stack.push(values.swap_remove(index));
+                self.ip += 1;
+            }
+            InstructionKind::ListSet => {
+                let value = self.pop_operand(&instruction)?;
+                let index = self.pop_operand(&instruction)?;
+                let mut values = self.pop_list(&instruction)?;
+                let index = self.list_index(index, values.len(), &instruction)?;
+
+                values[index] = value;
+                self.stack.push(Value::List(values));
+                self.ip += 1;
+            }
+            InstructionKind::Length => {
+                let length = match self.pop_operand(&instruction)? {
+                    Value::List(values) => values.len(),
+                    Value::String(string) => {
+                        self.program.strings[string.string_index].chars().count()
+                    }
+                    value => return Err(self.invalid_types(&instruction, &[&value])),
+                };
+
+                self.stack.push(Value::I64(length as i64));
+                self.ip += 1;
+            }
+            InstructionKind::ToString => {
+                let value = self.pop_operand(&instruction)?;
+                let string = self.format_value(&value);
+
+                let value = self.new_string(&string);
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::ReadLine => {
+                let value = match self.read_line(&instruction)? {
+                    Some(line) => self.new_string(&line),
+                    None => Value::Bool(false),
+                };
+
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::ReadAll => {
+                let mut input = String::new();
+                self.input
+                    .read_to_string(&mut input)
+                    .map_err(|error| RuntimeError::Io {
+                        message: error.to_string(),
+                        location: Some(instruction.location.clone()),
+                    })?;
+
+                let value = self.new_string(&input);
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::ReadInt => {
+                let value = match self.read_line(&instruction)? {
+                    Some(line) => match line.trim().parse() {
+                        Ok(value) => Value::I64(value),
+                        Err(_) => {
+                            return Err(RuntimeError::InvalidInteger(
+                                line,
+                                instruction.location.clone(),
+                            ))
                         }
-                    };
+                    },
+                    None => Value::Bool(false),
+                };
+
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::FileRead => {
+                let path = self.pop_path(&instruction)?;
+                let contents = fs::read_to_string(&path)
+                    .map_err(|error| file_error(&path, error, &instruction))?;
+
+                let value = self.new_string(&contents);
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::FileWrite | InstructionKind::FileAppend => {
+                let path = self.pop_path(&instruction)?;
+                let contents = self.pop_string(&instruction)?;
+
+                let append = matches!(instruction.kind, InstructionKind::FileAppend);
+                fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(append)
+                    .truncate(!append)
+                    .open(&path)
+                    .and_then(|mut file| file.write_all(contents.as_bytes()))
+                    .map_err(|error| file_error(&path, error, &instruction))?;
+
+                self.ip += 1;
+            }
+            InstructionKind::FileExists => {
+                let path = self.pop_path(&instruction)?;
 
-                    self.stack.push(left.clone());
-                    self.stack.push(right);
-                    self.stack.push(left);
+                self.stack.push(Value::Bool(fs::metadata(&path).is_ok()));
+                self.ip += 1;
+            }
+            InstructionKind::FileLines => {
+                let path = self.pop_path(&instruction)?;
+                let contents = fs::read_to_string(&path)
+                    .map_err(|error| file_error(&path, error, &instruction))?;
 
-                    self.ip += 1;
+                let lines = contents.lines().map(|line| self.new_string(line)).collect();
+
+                self.push_list(lines, &instruction)?;
+                self.ip += 1;
+            }
+            InstructionKind::DirList => {
+                let path = self.pop_path(&instruction)?;
+                let mut names = fs::read_dir(&path)
+                    .and_then(|entries| {
+                        entries
+                            .map(|entry| {
+                                entry.map(|entry| entry.file_name().to_string_lossy().to_string())
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .map_err(|error| file_error(&path, error, &instruction))?;
+                names.sort();
+
+                let names = names.iter().map(|name| self.new_string(name)).collect();
+
+                self.push_list(names, &instruction)?;
+                self.ip += 1;
+            }
+            InstructionKind::Args => {
+                let args = self
+                    .args
+                    .clone()
+                    .iter()
+                    .map(|arg| self.new_string(arg))
+                    .collect();
+
+                self.push_list(args, &instruction)?;
+                self.ip += 1;
+            }
+            InstructionKind::EnvGet => {
+                let name = self.pop_string(&instruction)?;
+
+                let value = match std::env::var(&name) {
+                    Ok(value) => self.new_string(&value),
+                    Err(_) => Value::Bool(false),
+                };
+
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            InstructionKind::PushHandler(target) => {
+                self.handlers.push(Handler::new(
+                    self.bp,
+                    target,
+                    self.frames.len(),
+                    self.stack.len(),
+                    self.locals.len(),
+                ));
+                self.ip += 1;
+            }
+            InstructionKind::PopHandler => {
+                self.handlers.pop();
+                self.ip += 1;
+            }
+            InstructionKind::Throw => {
+                let value = self.pop_operand(&instruction)?;
+
+                let message = self.format_value(&value);
+                self.thrown = Some(value);
+
+                return Err(RuntimeError::Thrown {
+                    message,
+                    location: instruction.location.clone(),
+                });
+            }
+            InstructionKind::Panic => {
+                let value = self.pop_operand(&instruction)?;
+
+                return Err(RuntimeError::Panic(
+                    self.format_value(&value),
+                    instruction.location.clone(),
+                ));
+            }
+            InstructionKind::MakeRecord { type_id, fields } => {
+                if self.stack.len() < fields {
+                    return Err(RuntimeError::StackUnderflow(instruction.location.clone()));
                 }
-                InstructionKind::Print => {
-                    let value = match self.stack.pop() {
-                        Some(value) => match value {
-                            Value::String(Str { string_index, .. }) => {
-                                Value::RawString(self.program.strings[string_index].clone())
-                            }
-                            _ => value,
-                        },
-                        None => {
-                            return Err(RuntimeError::StackUnderflow(instruction.location.clone()))
-                        }
-                    };
 
-                    print!("{}", value);
-                    self.ip += 1;
+                let values = self.stack.split_off(self.stack.len() - fields);
+                self.stack.push(Value::Record(Record::new(type_id, values)));
+
+                self.ip += 1;
+            }
+            InstructionKind::MakeVariant {
+                type_id,
+                tag,
+                fields,
+            } => {
+                if self.stack.len() < fields {
+                    return Err(RuntimeError::StackUnderflow(instruction.location.clone()));
                 }
-                _ => todo!("Instruction not implemented: {:?}", instruction),
+
+                let values = self.stack.split_off(self.stack.len() - fields);
+                self.stack
+                    .push(Value::Variant(Variant::new(type_id, tag, values)));
+
+                self.ip += 1;
             }
+            InstructionKind::Match { type_id, ref table } => {
+                let variant = match self.stack.pop() {
+                    Some(Value::Variant(variant)) if variant.type_id == type_id => variant,
+                    Some(value) => return Err(self.invalid_types(&instruction, &[&value])),
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let target = match table[variant.tag] {
+                    Some(target) => target,
+                    None => {
+                        let definition = &self.program.enums[type_id].variants[variant.tag];
+
+                        return Err(RuntimeError::UnmatchedVariant(
+                            definition.name.clone(),
+                            instruction.location.clone(),
+                        ));
+                    }
+                };
+
+                self.stack.extend(variant.fields);
+                self.ip = target;
+            }
+            InstructionKind::GetField(ref field) => {
+                let record = match self.stack.pop() {
+                    Some(Value::Record(record)) => record,
+                    Some(value) => return Err(self.invalid_types(&instruction, &[&value])),
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let index = match self.record_field(&record, field) {
+                    Some(index) => index,
+                    None => {
+                        return Err(RuntimeError::UnknownField(
+                            field.clone(),
+                            instruction.location.clone(),
+                        ))
+                    }
+                };
+
+                self.stack.push(record.fields[index].clone());
+                self.ip += 1;
+            }
+            InstructionKind::SetField(ref field) => {
+                let value = match self.stack.pop() {
+                    Some(value) => value,
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let mut record = match self.stack.pop() {
+                    Some(Value::Record(record)) => record,
+                    Some(value) => return Err(self.invalid_types(&instruction, &[&value])),
+                    None => return Err(RuntimeError::StackUnderflow(instruction.location.clone())),
+                };
+
+                let index = match self.record_field(&record, field) {
+                    Some(index) => index,
+                    None => {
+                        return Err(RuntimeError::UnknownField(
+                            field.clone(),
+                            instruction.location.clone(),
+                        ))
+                    }
+                };
+
+                record.fields[index] = value;
+                self.stack.push(Value::Record(record));
+                self.ip += 1;
+            }
+            _ => todo!("Instruction not implemented: {:?}", instruction),
         }
 
-        let result = match self.stack.pop() {
-            Some(value) => match value {
-                Value::String(string) => {
-                    let string = self.program.strings[string.string_index].clone();
-                    Value::RawString(string)
-                }
-                _ => value,
-            },
-            None => Value::I64(0),
-        };
+        self.check_limits(&instruction.location)?;
 
-        Ok(result)
+        Ok(true)
+    }
+}
+
+fn file_error(path: &str, error: io::Error, instruction: &Instruction) -> RuntimeError {
+    RuntimeError::Io {
+        message: format!("{}: {}", path, error),
+        location: Some(instruction.location.clone()),
     }
 }
@@ -0,0 +1,116 @@
+use std::rc::Rc;
+
+use crate::{
+    compiler::builtins::builtin_instruction,
+    lexer::Lexer,
+    token::{Token, TokenKind},
+};
+
+use super::{value::Value, Interpreter};
+
+/// A Rust function callable from scripts. It receives the interpreter and its
+/// arguments (the deepest one first), and returns the values to push or a
+/// message describing why it failed.
+pub type NativeFunction = Rc<dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Vec<Value>, String>>;
+
+#[derive(Clone)]
+pub struct Native {
+    pub name: String,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub function: NativeFunction,
+}
+
+impl std::fmt::Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .finish()
+    }
+}
+
+/// Returned when registering a native under a name scripts can't call it by:
+/// a builtin word, a keyword or an operator.
+#[derive(Debug, Clone)]
+pub struct ReservedName(pub String);
+
+impl std::fmt::Display for ReservedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` can't be the name of a native word", self.0)
+    }
+}
+
+impl std::error::Error for ReservedName {}
+
+/// The native words an embedder exposes to scripts. They are resolved like
+/// functions, with the stack effect given by their declared arity. A script
+/// declaring a function with the name of a native is rejected.
+#[derive(Debug, Clone, Default)]
+pub struct Natives {
+    natives: Vec<Native>,
+}
+
+impl Natives {
+    pub fn new() -> Self {
+        Self {
+            natives: Vec::new(),
+        }
+    }
+
+    /// Registers `function` as the word `name`, taking `inputs` values from
+    /// the stack and pushing `outputs` values. Registering a name again
+    /// replaces the previous function.
+    pub fn register<F>(
+        &mut self,
+        name: &str,
+        inputs: usize,
+        outputs: usize,
+        function: F,
+    ) -> Result<&mut Self, ReservedName>
+    where
+        F: Fn(&mut Interpreter, Vec<Value>) -> Result<Vec<Value>, String> + 'static,
+    {
+        if is_reserved(name) {
+            return Err(ReservedName(name.to_string()));
+        }
+
+        let native = Native {
+            name: name.to_string(),
+            inputs,
+            outputs,
+            function: Rc::new(function),
+        };
+
+        match self.index(name) {
+            Some(index) => self.natives[index] = native,
+            None => self.natives.push(native),
+        }
+
+        Ok(self)
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.natives.iter().position(|native| native.name == name)
+    }
+
+    pub fn get(&self, index: usize) -> &Native {
+        &self.natives[index]
+    }
+}
+
+/// Whether scripts would reach something other than a native named `name`:
+/// builtin words take precedence, and keywords and operators aren't lexed as
+/// names at all.
+fn is_reserved(name: &str) -> bool {
+    if builtin_instruction(name).is_some() {
+        return true;
+    }
+
+    let tokens = Lexer::new(name, "").lex();
+    !matches!(
+        tokens.as_slice(),
+        [Token { kind: TokenKind::Identifier(word), .. }, _] if word == name
+    )
+}
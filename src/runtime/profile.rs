@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::token::location::Location;
+
+/// The instructions run and the time spent running them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cost {
+    pub instructions: u64,
+    pub time: Duration,
+}
+
+impl Cost {
+    fn add(&mut self, time: Duration) {
+        self.instructions += 1;
+        self.time += time;
+    }
+}
+
+/// The cost of a function: `exclusive` counts its own instructions, while
+/// `inclusive` also counts those of the functions it calls.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FunctionCost {
+    pub exclusive: Cost,
+    pub inclusive: Cost,
+}
+
+/// Measures every instruction the interpreter runs, attributing it to the
+/// function and source line it belongs to and to the calls that led there.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    functions: HashMap<usize, FunctionCost>,
+    lines: HashMap<(Arc<str>, usize), Cost>,
+    /// Instructions run under each chain of calls, outermost block first.
+    stacks: HashMap<Vec<usize>, u64>,
+    pub(super) labels: Vec<String>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
+            lines: HashMap::new(),
+            stacks: HashMap::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    /// Records an instruction at `location` that took `time`, run with the
+    /// blocks of `calls` active, the innermost one last.
+    pub(super) fn record(&mut self, calls: Vec<usize>, location: &Location, time: Duration) {
+        let Some(&current) = calls.last() else {
+            return;
+        };
+
+        self.functions
+            .entry(current)
+            .or_default()
+            .exclusive
+            .add(time);
+
+        // A recursive function is only charged once per instruction.
+        for (index, block) in calls.iter().enumerate() {
+            if !calls[..index].contains(block) {
+                self.functions
+                    .entry(*block)
+                    .or_default()
+                    .inclusive
+                    .add(time);
+            }
+        }
+
+        self.lines
+            .entry((location.file.clone(), location.line))
+            .or_default()
+            .add(time);
+
+        *self.stacks.entry(calls).or_default() += 1;
+    }
+
+    /// The cost of each function by name, the most expensive first.
+    pub fn functions(&self) -> Vec<(String, FunctionCost)> {
+        let mut functions = self
+            .functions
+            .iter()
+            .map(|(block, cost)| (self.labels[*block].clone(), *cost))
+            .collect::<Vec<_>>();
+
+        functions.sort_by(|(a_name, a), (b_name, b)| {
+            b.exclusive
+                .instructions
+                .cmp(&a.exclusive.instructions)
+                .then_with(|| a_name.cmp(b_name))
+        });
+
+        functions
+    }
+
+    /// The cost of each source line as `file:line`, the most expensive first.
+    pub fn lines(&self) -> Vec<(String, Cost)> {
+        let mut lines = self
+            .lines
+            .iter()
+            .map(|((file, line), cost)| (format!("{}:{}", file, line), *cost))
+            .collect::<Vec<_>>();
+
+        lines.sort_by(|(a_line, a), (b_line, b)| {
+            b.instructions
+                .cmp(&a.instructions)
+                .then_with(|| a_line.cmp(b_line))
+        });
+
+        lines
+    }
+
+    /// Writes a report of the cost of the functions and of the `lines` most
+    /// expensive source lines.
+    pub fn write_report(&self, output: &mut impl Write, lines: usize) -> io::Result<()> {
+        writeln!(
+            output,
+            "{:>12} {:>12} {:>12} {:>12}  function",
+            "self instr", "self time", "total instr", "total time"
+        )?;
+
+        for (name, cost) in self.functions() {
+            writeln!(
+                output,
+                "{:>12} {:>12} {:>12} {:>12}  {}",
+                cost.exclusive.instructions,
+                format!("{:.2?}", cost.exclusive.time),
+                cost.inclusive.instructions,
+                format!("{:.2?}", cost.inclusive.time),
+                name
+            )?;
+        }
+
+        writeln!(output)?;
+        writeln!(output, "{:>12} {:>12}  line", "instr", "time")?;
+
+        for (line, cost) in self.lines().into_iter().take(lines) {
+            writeln!(
+                output,
+                "{:>12} {:>12}  {}",
+                cost.instructions,
+                format!("{:.2?}", cost.time),
+                line
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the instructions run under each chain of calls in the folded
+    /// format read by flamegraph tools: `main;outer;inner 42`.
+    pub fn write_folded(&self, output: &mut impl Write) -> io::Result<()> {
+        let mut stacks = self
+            .stacks
+            .iter()
+            .map(|(calls, count)| {
+                let names = calls
+                    .iter()
+                    .map(|block| self.labels[*block].as_str())
+                    .collect::<Vec<_>>();
+
+                (names.join(";"), *count)
+            })
+            .collect::<Vec<_>>();
+        stacks.sort();
+
+        for (stack, count) in stacks {
+            writeln!(output, "{} {}", stack, count)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::Write;
+
+/// Logs every instruction the interpreter runs, with the function it belongs
+/// to, its source location and the top of the stack before and after it.
+pub struct Tracer {
+    pub(super) output: Box<dyn Write>,
+    /// The functions to trace, all of them when empty. Nested functions can
+    /// be named without their enclosing ones.
+    functions: Vec<String>,
+    pub(super) labels: Vec<String>,
+    /// Whether each block is traced, worked out once from `functions`.
+    traced: Vec<bool>,
+}
+
+impl std::fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tracer")
+            .field("functions", &self.functions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tracer {
+    pub fn new(output: impl Write + 'static) -> Self {
+        Self {
+            output: Box::new(output),
+            functions: Vec::new(),
+            labels: Vec::new(),
+            traced: Vec::new(),
+        }
+    }
+
+    /// Only traces the instructions of `functions`.
+    pub fn with_functions(mut self, functions: Vec<String>) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// Sets the labels of the program's blocks and which of them to trace.
+    pub(super) fn set_labels(&mut self, labels: Vec<String>) {
+        let suffixes: Vec<String> = self
+            .functions
+            .iter()
+            .map(|function| format!("/{}", function))
+            .collect();
+
+        self.traced = labels
+            .iter()
+            .map(|name| {
+                self.functions.is_empty()
+                    || self
+                        .functions
+                        .iter()
+                        .zip(&suffixes)
+                        .any(|(function, suffix)| function == name || name.ends_with(suffix))
+            })
+            .collect();
+        self.labels = labels;
+    }
+
+    pub(super) fn traces(&self, block: usize) -> bool {
+        self.traced[block]
+    }
+}
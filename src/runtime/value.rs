@@ -5,6 +5,10 @@ pub enum Value {
     Bool(bool),
     String(Str),
     RawString(String),
+    Closure(Closure),
+    Record(Record),
+    Variant(Variant),
+    List(Vec<Value>),
 }
 
 impl Value {
@@ -15,11 +19,26 @@ impl Value {
             (Value::Bool(a), Value::Bool(b)) => Value::Bool(a == b),
             (Value::String(a), Value::String(b)) => Value::Bool(a.string_index == b.string_index),
             (Value::RawString(a), Value::RawString(b)) => Value::Bool(a == b),
+            (Value::Record(a), Value::Record(b)) => {
+                Value::Bool(a.type_id == b.type_id && fields_equal(&a.fields, &b.fields))
+            }
+            (Value::List(a), Value::List(b)) => {
+                Value::Bool(a.len() == b.len() && fields_equal(a, b))
+            }
+            (Value::Variant(a), Value::Variant(b)) => Value::Bool(
+                a.type_id == b.type_id && a.tag == b.tag && fields_equal(&a.fields, &b.fields),
+            ),
             _ => Value::Bool(false),
         }
     }
 }
 
+fn fields_equal(a: &[Value], b: &[Value]) -> bool {
+    a.iter()
+        .zip(b.iter())
+        .all(|(a, b)| matches!(a.equals(b), Value::Bool(true)))
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -28,6 +47,10 @@ impl std::fmt::Display for Value {
             Value::Bool(b) => write!(f, "{}", b),
             Value::String(s) => write!(f, "{}", s),
             Value::RawString(s) => write!(f, "{}", s),
+            Value::Closure(c) => write!(f, "{}", c),
+            Value::Record(r) => write!(f, "{}", r),
+            Value::Variant(v) => write!(f, "{}", v),
+            Value::List(l) => write!(f, "list of {} values", l.len()),
         }
     }
 }
@@ -50,6 +73,69 @@ impl Str {
 
 impl std::fmt::Display for Str {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "str pointer to {}", self.string_index)
+        write!(
+            f,
+            "str pointer to {} ({} bytes)",
+            self.string_index, self.length
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub block: usize,
+    pub captures: Vec<Value>,
+}
+
+impl Closure {
+    pub fn new(block: usize, captures: Vec<Value>) -> Self {
+        Self { block, captures }
+    }
+}
+
+impl std::fmt::Display for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "closure at block {}", self.block)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub type_id: usize,
+    pub fields: Vec<Value>,
+}
+
+impl Record {
+    pub fn new(type_id: usize, fields: Vec<Value>) -> Self {
+        Self { type_id, fields }
+    }
+}
+
+impl std::fmt::Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "record of type {}", self.type_id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub type_id: usize,
+    pub tag: usize,
+    pub fields: Vec<Value>,
+}
+
+impl Variant {
+    pub fn new(type_id: usize, tag: usize, fields: Vec<Value>) -> Self {
+        Self {
+            type_id,
+            tag,
+            fields,
+        }
+    }
+}
+
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "variant {} of type {}", self.tag, self.type_id)
     }
 }
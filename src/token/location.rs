@@ -1,20 +1,22 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 #[derive(Debug, Clone)]
 pub struct Location {
+    pub file: Arc<str>,
     pub line: usize,
     pub column: usize,
 }
 
 impl Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "INSERT_FILE_HERE:{}:{}", self.line, self.column)
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
     }
 }
 
 impl Location {
     pub fn combine(&self, other: &Self) -> Self {
         Self {
+            file: self.file.clone(),
             line: self.line.min(other.line),
             column: self.column.min(other.column),
         }
@@ -49,14 +49,25 @@ pub enum TokenKind {
     False,
     Call,
     Do,
+    Struct,
+    Enum,
+    Match,
+    Case,
+    Import,
+    Const,
+    Var,
+    For,
+    Step,
+    Try,
+    Catch,
 
     Identifier(String),
     String(String),
     Integer(i64),
     Float(f64),
 
-    Error,
-    EOF,
+    Error(String),
+    Eof,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,65 @@
+mod common;
+
+use stack_based_lang::{DepthAnalyzer, TypeChecker};
+
+use common::compile;
+
+/// The diagnostics of the depth analysis of `source`.
+fn depth(source: &str) -> Vec<String> {
+    DepthAnalyzer::new(&compile(source))
+        .analyze()
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+#[test]
+fn balanced_programs_have_no_depth_diagnostics() {
+    assert!(depth("fun main do 1 2 + println end").is_empty());
+}
+
+#[test]
+fn underflows_are_found_before_running() {
+    assert_eq!(
+        depth("fun main do 1 + println end"),
+        ["error: Stack underflow: `add` needs 2 value(s) but only 1 are available at test.shark:1:15"]
+    );
+}
+
+#[test]
+fn underflows_are_found_through_calls() {
+    assert_eq!(
+        depth("fun pop-two do drop drop end fun main do 1 pop-two end"),
+        ["error: Stack underflow: `pop-two` needs 2 value(s) but only 1 are available at test.shark:1:44"]
+    );
+}
+
+/// The diagnostics of the type checker on `source`.
+fn types(source: &str) -> Vec<String> {
+    TypeChecker::new(&compile(source))
+        .check()
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+#[test]
+fn well_typed_programs_have_no_type_diagnostics() {
+    assert!(types(r#"fun main do 1 2.5 + println "a" "b" + println end"#).is_empty());
+}
+
+#[test]
+fn mismatched_operands_are_found_before_running() {
+    assert_eq!(
+        types(r#"fun main do "a" 1 + println end"#),
+        ["error: cannot add String and Int at test.shark:1:19"]
+    );
+}
+
+#[test]
+fn conditions_must_be_booleans() {
+    assert_eq!(
+        types("fun main do 1 if 2 println end end"),
+        ["error: condition must be a Bool, found Int at test.shark:1:15"]
+    );
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::process::Command;
+
+use stack_based_lang::Interpreter;
+
+use common::{capture, compile};
+
+#[test]
+fn args_are_pushed_as_a_list_of_strings() {
+    let interpreter = Interpreter::new(compile("fun main do args dup println 1 nth println end"))
+        .with_args(vec!["a".to_string(), "b c".to_string()]);
+
+    let (result, output) = capture(interpreter);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "[\"a\", \"b c\"]\nb c\n");
+}
+
+#[test]
+fn env_get_pushes_false_for_unset_variables() {
+    let (result, output) = capture(Interpreter::new(compile(
+        r#"fun main do "STACK_BASED_LANG_TEST_UNSET" env-get println end"#,
+    )));
+
+    assert!(result.is_ok());
+    assert_eq!(output, "false\n");
+}
+
+#[test]
+fn the_command_line_passes_arguments_and_the_environment_through() {
+    let path = std::env::temp_dir().join("stack-based-lang-args.shark");
+    std::fs::write(
+        &path,
+        r#"fun main do args length println "GREETING" env-get println end"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stack-based-lang"))
+        .arg("run")
+        .arg(&path)
+        .args(["--", "a", "--trace", "c"])
+        .env("GREETING", "hello")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    // The program and its result are dumped around what it prints.
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\n3\nhello\n"));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn closures_capture_let_bindings() {
+    let (result, output) = run("
+        fun make-adder (n -> f) do
+            let n in [ n + ] end
+        end
+
+        fun main do
+            5 make-adder 10 make-adder
+            let add-five add-ten in
+                1 add-five apply println
+                1 add-five apply add-ten apply println
+            end
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "6\n16\n");
+}
+
+#[test]
+fn closures_capture_bindings_by_value() {
+    let (result, output) = run("
+        fun main do
+            1 let n in
+                [ n ]
+                2 let n in [ n ] end
+            end
+            apply println apply println
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "2\n1\n");
+}
+
+#[test]
+fn nested_closures_capture_from_every_enclosing_scope() {
+    let (result, output) = run("
+        fun main do
+            1 let a in
+                [ 2 let b in [ a b + ] end ]
+            end
+            apply apply println
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "3\n");
+}
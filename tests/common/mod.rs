@@ -0,0 +1,71 @@
+// Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use stack_based_lang::{
+    compile_source, Error, Interpreter, Natives, Program, ResolverError, RuntimeError,
+};
+
+/// Compiles `source` along with the standard library.
+pub fn compile(source: &str) -> Program {
+    compile_with_natives(source, &Natives::new())
+}
+
+/// Compiles `source` along with the standard library, letting it call the
+/// words registered in `natives`.
+pub fn compile_with_natives(source: &str, natives: &Natives) -> Program {
+    match compile_source(source, "test.shark", true, natives) {
+        Ok(compilation) => compilation.program,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// Compiles `source` along with the standard library, expecting the resolver
+/// to reject it.
+pub fn resolver_errors(source: &str) -> Vec<ResolverError> {
+    match compile_source(source, "test.shark", true, &Natives::new()) {
+        Err(Error::Resolver(errors)) => errors,
+        Err(error) => panic!("unexpected error: {}", error),
+        Ok(_) => panic!("the program should not compile"),
+    }
+}
+
+/// Runs `source` to completion, returning how it stopped and what it printed.
+pub fn run(source: &str) -> (Result<(), RuntimeError>, String) {
+    capture(Interpreter::new(compile(source)))
+}
+
+/// Runs `interpreter` to completion with its output captured, returning how
+/// it stopped and what it printed.
+pub fn capture(interpreter: Interpreter) -> (Result<(), RuntimeError>, String) {
+    let output = Output::default();
+    let mut interpreter = interpreter.with_output(output.clone());
+
+    let result = interpreter.interpret().map(|_| ());
+    (result, output.contents())
+}
+
+/// A writer whose contents can still be read once an interpreter owns it.
+#[derive(Debug, Clone, Default)]
+pub struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
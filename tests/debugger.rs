@@ -0,0 +1,85 @@
+mod common;
+
+use stack_based_lang::{Debugger, Interpreter};
+
+use common::{compile, Output};
+
+const PROGRAM: &str = "fun inner (n -> n) do
+    1 +
+end
+
+fun outer (n -> n) do
+    inner
+    2 *
+end
+
+fun main do
+    1 outer
+    println
+end
+";
+
+/// Runs a debugging session of `PROGRAM` reading `commands`, returning the
+/// debugger's transcript and what the program printed.
+fn debug(commands: &str) -> (String, String) {
+    let output = Output::default();
+    let interpreter = Interpreter::new(compile(PROGRAM)).with_output(output.clone());
+
+    let mut transcript = Vec::new();
+    Debugger::new(interpreter)
+        .run(commands.as_bytes(), &mut transcript)
+        .unwrap();
+
+    (String::from_utf8(transcript).unwrap(), output.contents())
+}
+
+#[test]
+fn function_breakpoints_stop_on_entry_and_show_the_frames() {
+    let (transcript, output) = debug("break inner\ncontinue\nbt\nstack\nfinish\ncontinue\nbt\n");
+
+    assert_eq!(
+        transcript,
+        "Type `help` for a list of commands.
+Stopped at test.shark:11:5
+(debug) Breakpoint 1 at `inner`
+(debug) Stopped at test.shark:2:5
+(debug) #0 `inner` at test.shark:2:5
+#1 `outer` at test.shark:6:5
+#2 `main` at test.shark:11:7
+(debug) [1]
+(debug) Stopped at test.shark:7:5
+(debug) The program finished
+(debug) The program is not running
+(debug) "
+    );
+    assert_eq!(output, "4\n");
+}
+
+#[test]
+fn stepping_enters_calls_and_next_steps_over_them() {
+    let (transcript, output) = debug("step\nnext\nbreak test.shark:12\ncontinue\nstep\n");
+
+    assert_eq!(
+        transcript,
+        "Type `help` for a list of commands.
+Stopped at test.shark:11:5
+(debug) Stopped at test.shark:6:5
+(debug) Stopped at test.shark:7:5
+(debug) Breakpoint 1 at test.shark:12
+(debug) Stopped at test.shark:12:5
+(debug) The program finished
+(debug) "
+    );
+    assert_eq!(output, "4\n");
+}
+
+#[test]
+fn an_empty_line_repeats_the_previous_command() {
+    let (transcript, _) = debug("step\n\n");
+
+    assert!(transcript.ends_with(
+        "(debug) Stopped at test.shark:6:5
+(debug) Stopped at test.shark:2:5
+(debug) "
+    ));
+}
@@ -0,0 +1,76 @@
+use stack_based_lang::{compile_source, Error, Interpreter, Natives, RuntimeError, Value};
+
+fn compile_error(source: &str) -> String {
+    match compile_source(source, "test.shark", false, &Natives::new()) {
+        Ok(_) => panic!("expected `{}` not to compile", source),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn malformed_programs_are_reported() {
+    assert_eq!(
+        compile_error("fun main do 1 2 +"),
+        "Expected End, found the end of the file at test.shark:1:18"
+    );
+    assert_eq!(
+        compile_error("fun do end"),
+        "Expected a function name, found Do at test.shark:1:5"
+    );
+    assert_eq!(
+        compile_error("fun main do 1 ) end"),
+        "Expected an expression, found CloseParen at test.shark:1:15"
+    );
+    assert_eq!(
+        compile_error("fun main do let in end end"),
+        "Expected a binding name, found In at test.shark:1:17"
+    );
+}
+
+#[test]
+fn malformed_tokens_are_reported() {
+    assert_eq!(
+        compile_error(r#"fun main do "open end"#),
+        "Unterminated string at test.shark:1:13"
+    );
+    assert_eq!(
+        compile_error(r#"fun main do "\q" end"#),
+        "Invalid escape sequence `\\q` at test.shark:1:13"
+    );
+    assert_eq!(
+        compile_error("fun main do 99999999999999999999 end"),
+        "Invalid integer `99999999999999999999` at test.shark:1:13"
+    );
+}
+
+#[test]
+fn parse_errors_are_loader_errors() {
+    let error = compile_source("fun main do", "test.shark", false, &Natives::new());
+    assert!(matches!(error, Err(Error::Loader(_))));
+}
+
+#[test]
+fn hosts_read_strings_returned_by_the_program() {
+    let source = r#"
+        fun greet (name -> greeting) do "hello, " swap + end
+        fun main do end
+    "#;
+    let compilation = compile_source(source, "test.shark", true, &Natives::new()).unwrap();
+    let mut interpreter = Interpreter::new(compilation.program);
+
+    let name = interpreter.new_string("world");
+    interpreter.push(name);
+    interpreter.call("greet").unwrap();
+
+    let greeting = interpreter.pop().unwrap();
+    assert_eq!(interpreter.string(&greeting), Some("hello, world"));
+    assert_eq!(interpreter.string(&Value::I64(1)), None);
+}
+
+#[test]
+fn errors_can_cross_threads() {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    assert_send_sync::<Error>();
+    assert_send_sync::<RuntimeError>();
+}
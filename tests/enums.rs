@@ -0,0 +1,72 @@
+mod common;
+
+use stack_based_lang::{compile_source, Natives};
+
+use common::run;
+
+const SHAPES: &str = "
+    enum Shape Circle(r) | Rect(w h) | Empty end
+
+    fun area (shape -> area) do
+        match
+            case Circle do dup * 3 * end
+            case Rect do * end
+            case Empty do 0 end
+        end
+    end
+";
+
+#[test]
+fn match_dispatches_on_the_variant_and_pushes_its_fields() {
+    let (result, output) = run(&format!(
+        "{}
+        fun main do
+            2 Circle area println
+            3 4 Rect dup println area println
+            Empty area println
+        end",
+        SHAPES
+    ));
+
+    assert!(result.is_ok());
+    assert_eq!(output, "12\nRect(3, 4)\n12\n0\n");
+}
+
+#[test]
+fn non_exhaustive_matches_are_warned_about() {
+    let source = "
+        enum Shape Circle(r) | Rect(w h) | Empty end
+
+        fun main do
+            Empty match case Circle do drop end end
+        end
+    ";
+    let compilation = compile_source(source, "test.shark", true, &Natives::new()).unwrap();
+
+    let warnings = compilation
+        .warnings
+        .iter()
+        .map(|warning| warning.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        ["warning: Non-exhaustive match on `Shape`: missing Rect, Empty at test.shark:5:19"]
+    );
+}
+
+#[test]
+fn variants_are_matched_once() {
+    let source = "
+        enum Shape Circle(r) | Empty end
+
+        fun main do
+            Empty match case Empty do end case Empty do end end
+        end
+    ";
+    let error = compile_source(source, "test.shark", true, &Natives::new()).unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Variant `Empty` is matched more than once at test.shark:5:43"
+    );
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use stack_based_lang::{Interpreter, RuntimeError};
+
+use common::{compile, run};
+
+#[test]
+fn try_catches_runtime_errors() {
+    let (result, output) = run("
+        fun main do
+            try 1 0 / catch .kind end println
+            try 9223372036854775807 1 + catch .kind end println
+            try 4611686018427387904 2 * catch .message end println
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(
+        output,
+        "DivisionByZero\nIntegerOverflow\nInteger overflow at test.shark:5:39\n"
+    );
+}
+
+#[test]
+fn try_catches_thrown_values() {
+    let (result, output) = run(r#"
+        fun main do
+            try 42 throw catch 1 + end println
+            try try "inner" throw catch drop "outer" throw end catch end println
+        end
+    "#);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "43\nouter\n");
+}
+
+#[test]
+fn throw_can_guard_a_branch() {
+    let (result, output) = run(r#"
+        fun safe-div (a b -> r) do
+            dup 0 == if "division by zero" throw else / end
+        end
+
+        fun main do
+            6 3 safe-div println
+            try 1 0 safe-div catch end println
+        end
+    "#);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "2\ndivision by zero\n");
+}
+
+#[test]
+fn uncaught_errors_have_a_backtrace() {
+    let (result, _) = run("
+        fun inner do [ 1 0 / drop ] apply end
+        fun outer do inner end
+        fun main do outer end
+    ");
+
+    let error = result.expect_err("the division should fail");
+    assert!(matches!(error.cause(), RuntimeError::DivisionByZero(_)));
+
+    let functions = error
+        .backtrace()
+        .iter()
+        .map(|frame| frame.function.as_str())
+        .collect::<Vec<_>>();
+    assert!(functions[0].starts_with("closure@"));
+    assert_eq!(functions[1..], ["inner", "outer", "main"]);
+
+    let message = error.to_string();
+    assert!(message.starts_with("Division by zero at test.shark:2:28\n    in `closure@"));
+    assert!(message.ends_with(
+        "\n    in `inner` at test.shark:2:37\n    in `outer` at test.shark:3:22\n    in `main` at test.shark:4:21"
+    ));
+}
+
+#[test]
+fn backtraces_of_host_calls_stop_at_the_called_function() {
+    let mut interpreter = Interpreter::new(compile(
+        "
+        fun fail do 1 0 / drop end
+        fun wrapper do fail end
+        fun main do end
+        ",
+    ));
+
+    let error = interpreter.call("wrapper").unwrap_err();
+
+    assert!(matches!(error.cause(), RuntimeError::DivisionByZero(_)));
+    let functions = error
+        .backtrace()
+        .iter()
+        .map(|frame| frame.function.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(functions, ["fail", "wrapper"]);
+}
+
+#[test]
+fn uncaught_thrown_values_have_a_backtrace() {
+    let (result, _) = run(r#"
+        fun fail do "nope" throw end
+        fun main do fail end
+    "#);
+
+    let error = result.expect_err("the value should not be caught");
+    assert_eq!(error.kind(), "Thrown");
+    assert_eq!(
+        error.to_string(),
+        "Uncaught exception nope at test.shark:2:28\n    in `fail` at test.shark:2:28\n    in `main` at test.shark:3:21"
+    );
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use stack_based_lang::Interpreter;
+
+use common::{capture, compile, run};
+
+/// A fresh, empty directory named after the test.
+fn directory(test: &str) -> PathBuf {
+    let directory = std::env::temp_dir()
+        .join("stack-based-lang-files")
+        .join(test);
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+#[test]
+fn files_are_written_appended_and_read() {
+    let path = directory("round-trip").join("notes.txt");
+    let (result, output) = run(&format!(
+        r#"
+        fun main do
+            {path:?} file-exists println
+            "one\n" {path:?} file-write
+            "two\n" {path:?} file-append
+            {path:?} file-exists println
+            {path:?} file-read print
+            {path:?} file-lines length println
+        end
+        "#
+    ));
+
+    assert!(result.is_ok());
+    assert_eq!(output, "false\ntrue\none\ntwo\n2\n");
+    assert_eq!(fs::read_to_string(path).unwrap(), "one\ntwo\n");
+}
+
+#[test]
+fn directories_are_listed_by_name() {
+    let directory = directory("listing");
+    fs::write(directory.join("b.txt"), "").unwrap();
+    fs::write(directory.join("a.txt"), "").unwrap();
+
+    let (result, output) = run(&format!("fun main do {:?} dir-list println end", directory));
+
+    assert!(result.is_ok());
+    assert_eq!(output, "[\"a.txt\", \"b.txt\"]\n");
+}
+
+#[test]
+fn failures_are_catchable_and_carry_the_os_message() {
+    let path = directory("missing").join("missing.txt");
+    let (result, output) = run(&format!(
+        "fun main do try {:?} file-read catch dup .kind println .message end println end",
+        path
+    ));
+
+    assert!(result.is_ok());
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "IoError");
+    assert!(lines[1].contains(&format!("{}: No such file or directory", path.display())));
+}
+
+#[test]
+fn the_sandbox_disables_the_file_system() {
+    let path = directory("sandbox").join("blocked.txt");
+    let source = format!(
+        r#"fun main do try "text" {:?} file-write "written" catch .kind end println end"#,
+        path
+    );
+
+    let (result, output) = capture(Interpreter::new(compile(&source)).with_sandbox(true));
+
+    assert!(result.is_ok());
+    assert_eq!(output, "Sandboxed\n");
+    assert!(!path.exists());
+}
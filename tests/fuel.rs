@@ -0,0 +1,139 @@
+mod common;
+
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use stack_based_lang::{Interpreter, RuntimeError};
+
+use common::{compile, Output};
+
+/// A writer counting how many times it is flushed.
+#[derive(Debug, Clone, Default)]
+struct Flushes(Rc<Cell<usize>>);
+
+impl Write for Flushes {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.set(self.0.get() + 1);
+        Ok(())
+    }
+}
+
+const COUNT: &str = "
+    fun main do
+        0 for i in 0..100 do i + end println
+    end
+";
+
+#[test]
+fn running_out_of_fuel_pauses_the_program() {
+    let output = Output::default();
+    let mut interpreter = Interpreter::new(compile(COUNT))
+        .with_output(output.clone())
+        .with_fuel(50);
+
+    let mut refuels = 0;
+
+    loop {
+        match interpreter.interpret() {
+            Ok(_) => break,
+            Err(error) if matches!(error.cause(), RuntimeError::OutOfFuel(_)) => {
+                assert_eq!(interpreter.fuel(), Some(0));
+                interpreter.refuel(50);
+                refuels += 1;
+            }
+            Err(error) => panic!("unexpected error: {}", error),
+        }
+    }
+
+    assert!(refuels > 1);
+    assert_eq!(output.contents(), "4950\n");
+}
+
+#[test]
+fn unused_fuel_is_kept() {
+    let mut interpreter = Interpreter::new(compile("fun main do 1 drop end"))
+        .with_output(Output::default())
+        .with_fuel(1000);
+
+    assert!(interpreter.interpret().is_ok());
+
+    let fuel = interpreter.fuel().unwrap_or_default();
+    assert!(fuel > 0 && fuel < 1000);
+}
+
+#[test]
+fn try_does_not_catch_running_out_of_fuel() {
+    let source = "
+        fun main do
+            try 0 while true do 1 + end catch end drop
+        end
+    ";
+    let mut interpreter = Interpreter::new(compile(source)).with_fuel(1000);
+
+    let error = interpreter.interpret().expect_err("the loop never ends");
+    assert!(error.is_interruption());
+    assert!(matches!(error.cause(), RuntimeError::OutOfFuel(_)));
+    assert_eq!(error.backtrace()[0].function, "main");
+}
+
+#[test]
+fn passing_the_deadline_stops_the_program() {
+    let source = "fun main do while true do end end";
+    let mut interpreter =
+        Interpreter::new(compile(source)).with_deadline(Instant::now() + Duration::from_millis(50));
+
+    let error = interpreter.interpret().expect_err("the loop never ends");
+    assert!(matches!(error.cause(), RuntimeError::Timeout(_)));
+}
+
+#[test]
+fn a_timed_out_program_can_be_resumed() {
+    let output = Output::default();
+    let mut interpreter = Interpreter::new(compile(COUNT))
+        .with_output(output.clone())
+        .with_deadline(Instant::now());
+
+    let error = interpreter
+        .interpret()
+        .expect_err("the deadline has passed");
+    assert!(matches!(error.cause(), RuntimeError::Timeout(_)));
+    assert_eq!(output.contents(), "");
+
+    interpreter.set_deadline(None);
+    assert!(interpreter.interpret().is_ok());
+    assert_eq!(output.contents(), "4950\n");
+}
+
+#[test]
+fn timing_out_uses_no_fuel() {
+    let mut interpreter = Interpreter::new(compile(COUNT))
+        .with_output(Output::default())
+        .with_fuel(10)
+        .with_deadline(Instant::now());
+
+    let error = interpreter
+        .interpret()
+        .expect_err("the deadline has passed");
+    assert!(matches!(error.cause(), RuntimeError::Timeout(_)));
+    assert_eq!(interpreter.fuel(), Some(10));
+}
+
+#[test]
+fn stepping_flushes_the_output_once_the_program_stops() {
+    let flushes = Flushes::default();
+    let mut interpreter = Interpreter::new(compile(COUNT)).with_output(flushes.clone());
+
+    while interpreter.step().expect("the program succeeds") {
+        assert_eq!(flushes.0.get(), 0);
+    }
+
+    assert_eq!(flushes.0.get(), 1);
+}
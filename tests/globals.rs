@@ -0,0 +1,117 @@
+mod common;
+
+use stack_based_lang::{compile_source, Natives};
+
+use common::run;
+
+fn compile_error(source: &str) -> String {
+    match compile_source(source, "test.shark", true, &Natives::new()) {
+        Ok(_) => panic!("expected `{}` not to compile", source),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn folded_division_matches_the_runtime() {
+    let (result, output) = run("
+        const HALF 1 2 / end
+        const MIXED 7 2.0 / end
+        const REMAINDER 7 2 % end
+
+        fun main do
+            HALF println 1 2 / println
+            MIXED println 7 2.0 / println
+            REMAINDER println 7 2 % println
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "0.5\n0.5\n3.5\n3.5\n1\n1\n");
+}
+
+#[test]
+fn folded_division_by_zero_is_an_error() {
+    let (result, _) = run("fun main do 1 0 / end");
+    assert_eq!(result.unwrap_err().kind(), "DivisionByZero");
+
+    assert_eq!(
+        compile_error("const C 1 0 / end fun main do end"),
+        "Invalid constant `C`: division by zero at test.shark:1:13"
+    );
+    assert_eq!(
+        compile_error("const C 1.5 0 % end fun main do end"),
+        "Invalid constant `C`: division by zero at test.shark:1:15"
+    );
+}
+
+#[test]
+fn locals_cannot_shadow_globals() {
+    assert_eq!(
+        compile_error("var x fun main do 1 let x in x ! end end"),
+        "Local `x` shadows the global variable `x` at test.shark:1:21"
+    );
+    assert_eq!(
+        compile_error("var i fun main do for i in 0..3 do end end"),
+        "Local `i` shadows the global variable `i` at test.shark:1:19"
+    );
+}
+
+#[test]
+fn constants_can_be_built_from_other_constants() {
+    let (result, output) = run("
+        const LIMIT 10 end
+        const TWICE LIMIT 2 * end
+        const GREETING \"hi \" \"there\" + end
+
+        fun main do LIMIT println TWICE println GREETING println end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "10\n20\nhi there\n");
+}
+
+#[test]
+fn globals_are_shared_between_functions() {
+    let (result, output) = run("
+        var calls
+
+        fun count-call do calls @ 1 + calls ! end
+
+        fun main do
+            0 calls !
+            count-call count-call count-call
+            calls @ println
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "3\n");
+}
+
+#[test]
+fn globals_are_declared_once_at_the_top_level() {
+    assert_eq!(
+        compile_error("var c var c fun main do end"),
+        "`c` is already declared at test.shark:1:7"
+    );
+    assert_eq!(
+        compile_error("fun main do var c end"),
+        "`c` must be declared at the top level at test.shark:1:13"
+    );
+}
+
+#[test]
+fn globals_are_fetched_and_stored_explicitly() {
+    assert_eq!(
+        compile_error("var c fun main do c println end"),
+        "Global variable `c` must be used as `c @` or `c !` at test.shark:1:19"
+    );
+}
+
+#[test]
+fn constants_must_fold_to_a_value() {
+    assert_eq!(
+        compile_error("const C \"a\" 1 + end fun main do end"),
+        "Invalid constant `C`: cannot apply `+` to \"a\" and 1 at test.shark:1:15"
+    );
+}
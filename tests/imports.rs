@@ -0,0 +1,122 @@
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use stack_based_lang::{compile_file, Error, Interpreter, LoaderError, Natives};
+
+use common::capture;
+
+/// Writes `files` to a fresh directory named after the test and returns it.
+fn project(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir()
+        .join("stack-based-lang-tests")
+        .join(test);
+    let _ = fs::remove_dir_all(&directory);
+
+    for (name, source) in files {
+        let path = directory.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+
+    directory
+}
+
+fn compile_project(directory: &Path) -> Result<Interpreter, Error> {
+    let main = directory.join("main.shark");
+    let compilation = compile_file(main.to_str().unwrap(), true, &Natives::new())?;
+
+    Ok(Interpreter::new(compilation.program))
+}
+
+#[test]
+fn imported_functions_are_qualified_by_their_module() {
+    let directory = project(
+        "qualified",
+        &[
+            (
+                "main.shark",
+                "import \"math.shark\"\nimport text::lines\n\nfun main do 3 4 math::sum-of-squares lines::show end",
+            ),
+            ("math.shark", "fun square (n -> n) do dup * end\nfun sum-of-squares (a b -> n) do square swap square + end"),
+            ("text/lines.shark", "fun show (s ->) do print \"\\n\" print end"),
+        ],
+    );
+
+    let (result, output) = capture(compile_project(&directory).unwrap());
+
+    assert!(result.is_ok());
+    assert_eq!(output, "25\n");
+}
+
+#[test]
+fn modules_imported_twice_are_loaded_once() {
+    let directory = project(
+        "diamond",
+        &[
+            (
+                "main.shark",
+                "import \"a.shark\"\nimport \"b.shark\"\n\nfun main do a::one b::two + println end",
+            ),
+            (
+                "a.shark",
+                "import \"c.shark\"\nfun one (-> n) do c::value end",
+            ),
+            (
+                "b.shark",
+                "import \"c.shark\"\nfun two (-> n) do c::value 1 + end",
+            ),
+            ("c.shark", "fun value (-> n) do 1 end"),
+        ],
+    );
+
+    let (result, output) = capture(compile_project(&directory).unwrap());
+
+    assert!(result.is_ok());
+    assert_eq!(output, "3\n");
+}
+
+#[test]
+fn import_cycles_are_reported() {
+    let directory = project(
+        "cycle",
+        &[
+            ("main.shark", "import \"a.shark\"\nfun main do end"),
+            ("a.shark", "import \"b.shark\"\nfun a do end"),
+            ("b.shark", "import \"a.shark\"\nfun b do end"),
+        ],
+    );
+
+    let error = compile_project(&directory).unwrap_err();
+
+    match error {
+        Error::Loader(LoaderError::ImportCycle { chain, location }) => {
+            assert!(chain.last().unwrap().ends_with("a.shark"));
+            assert!(location.file.ends_with("b.shark"));
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn errors_point_into_the_imported_file() {
+    let directory = project(
+        "located",
+        &[
+            ("main.shark", "import \"broken.shark\"\nfun main do end"),
+            ("broken.shark", "fun broken do\n  1 +\n"),
+        ],
+    );
+
+    let error = compile_project(&directory).unwrap_err();
+
+    match error {
+        Error::Loader(LoaderError::Parser(error)) => {
+            assert!(error.location().file.ends_with("broken.shark"));
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::io::Cursor;
+
+use stack_based_lang::{Interpreter, RuntimeError};
+
+use common::{capture, compile};
+
+fn run(source: &str, input: &str) -> (Result<(), RuntimeError>, String) {
+    capture(Interpreter::new(compile(source)).with_input(Cursor::new(input.to_string())))
+}
+
+#[test]
+fn output_goes_to_the_host_writer() {
+    let (result, output) = run(r#"fun main do "a" println 1 2 + println end"#, "");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "a\n3\n");
+}
+
+#[test]
+fn input_comes_from_the_host_reader() {
+    let (result, output) = run(
+        "
+        fun main do
+            read-line println
+            read-int read-int + println
+            read-all println
+        end
+        ",
+        "first line\n20\n22\nthe\nrest\n",
+    );
+
+    assert!(result.is_ok());
+    assert_eq!(output, "first line\n42\nthe\nrest\n\n");
+}
+
+#[test]
+fn reading_past_the_end_pushes_false() {
+    let (result, output) = run(
+        "
+        fun main do
+            read-line println
+            read-line println
+            read-int println
+        end
+        ",
+        "only\n",
+    );
+
+    assert!(result.is_ok());
+    assert_eq!(output, "only\nfalse\nfalse\n");
+}
+
+#[test]
+fn reading_an_invalid_integer_fails() {
+    let (result, output) = run(
+        "fun main do try read-int catch .kind end println end",
+        "twelve\n",
+    );
+
+    assert!(result.is_ok());
+    assert_eq!(output, "InvalidInteger\n");
+}
+
+#[test]
+fn output_is_flushed_when_the_program_fails() {
+    let (result, output) = run(r#"fun main do "before" println 1 0 / drop end"#, "");
+
+    assert!(result.is_err());
+    assert_eq!(output, "before\n");
+}
+
+#[test]
+fn input_can_be_read_until_it_runs_out() {
+    let (result, output) = run(
+        "
+        fun main do
+            0 while read-int dup false != do + end drop println
+        end
+        ",
+        "1\n2\n3\n",
+    );
+
+    assert!(result.is_ok());
+    assert_eq!(output, "6\n");
+}
+
+#[test]
+fn line_endings_and_surrounding_blanks_are_dropped() {
+    let (result, output) = run(
+        "
+        fun main do
+            read-line println
+            read-int println
+            read-all println
+        end
+        ",
+        "windows\r\n  -7  \n",
+    );
+
+    assert!(result.is_ok());
+    assert_eq!(output, "windows\n-7\n\n");
+}
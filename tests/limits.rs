@@ -0,0 +1,108 @@
+mod common;
+
+use stack_based_lang::{Interpreter, InterpreterConfig, Natives, RuntimeError, Value};
+
+use common::{capture, compile, compile_with_natives};
+
+fn run(source: &str, config: InterpreterConfig) -> Result<(), RuntimeError> {
+    capture(Interpreter::with_config(compile(source), config)).0
+}
+
+const COUNTDOWN: &str = "
+    fun countdown (n -> n) do
+        dup 0 > if 1 - countdown end
+    end
+
+    fun main do 20 countdown drop end
+";
+
+#[test]
+fn the_stack_depth_is_limited() {
+    let source = "fun main do 1 2 3 4 drop drop drop drop end";
+
+    assert!(run(source, InterpreterConfig::new().max_stack_depth(4)).is_ok());
+
+    let error = run(source, InterpreterConfig::new().max_stack_depth(3)).unwrap_err();
+    assert!(matches!(error.cause(), RuntimeError::StackOverflow(_)));
+}
+
+#[test]
+fn the_call_depth_is_limited() {
+    assert!(run(COUNTDOWN, InterpreterConfig::new().max_call_depth(25)).is_ok());
+
+    let error = run(COUNTDOWN, InterpreterConfig::new().max_call_depth(10)).unwrap_err();
+    assert!(matches!(error.cause(), RuntimeError::CallDepthExceeded(_)));
+}
+
+#[test]
+fn the_string_bytes_are_limited() {
+    let source = r#"
+        fun main do
+            "" for i in 0..10 do "abcde" + end drop
+        end
+    "#;
+
+    assert!(run(source, InterpreterConfig::new().max_string_bytes(1000)).is_ok());
+
+    let error = run(source, InterpreterConfig::new().max_string_bytes(100)).unwrap_err();
+    assert!(matches!(
+        error.cause(),
+        RuntimeError::StringBytesExceeded(_)
+    ));
+}
+
+#[test]
+fn the_collection_size_is_limited() {
+    let source = "
+        fun main do
+            list for i in 0..10 do i push end drop
+        end
+    ";
+
+    assert!(run(source, InterpreterConfig::new().max_collection_size(10)).is_ok());
+
+    let error = run(source, InterpreterConfig::new().max_collection_size(5)).unwrap_err();
+    assert!(matches!(
+        error.cause(),
+        RuntimeError::CollectionTooLarge(6, _)
+    ));
+}
+
+#[test]
+fn nothing_is_limited_by_default() {
+    assert!(run(COUNTDOWN, InterpreterConfig::new()).is_ok());
+}
+
+#[test]
+fn limits_cannot_be_caught() {
+    let source = r#"
+        fun main do
+            try list for i in 0..10 do i push end drop catch drop end
+        end
+    "#;
+
+    let error = run(source, InterpreterConfig::new().max_collection_size(5)).unwrap_err();
+    assert!(matches!(
+        error.cause(),
+        RuntimeError::CollectionTooLarge(6, _)
+    ));
+}
+
+#[test]
+fn lists_returned_by_natives_are_limited() {
+    let mut natives = Natives::new();
+    natives
+        .register("numbers", 0, 2, |_, _| {
+            Ok(vec![Value::List(vec![Value::I64(0); 20]), Value::I64(20)])
+        })
+        .unwrap();
+
+    let program = compile_with_natives("fun main do numbers drop drop end", &natives);
+    let config = InterpreterConfig::new().max_collection_size(10);
+    let (result, _) = capture(Interpreter::with_config(program, config));
+
+    assert!(matches!(
+        result.unwrap_err().cause(),
+        RuntimeError::CollectionTooLarge(20, _)
+    ));
+}
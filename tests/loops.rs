@@ -0,0 +1,55 @@
+mod common;
+
+use common::{resolver_errors, run};
+
+#[test]
+fn for_loops_count_up_to_the_end_of_the_range() {
+    let (result, output) = run(r#"
+        const N 3 end
+
+        fun main do
+            for i in 0..N do i print end "\n" print
+            for i in 5..5 do i print end "\n" print
+        end
+    "#);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "012\n\n");
+}
+
+#[test]
+fn for_loops_can_step_and_count_down() {
+    let (result, output) = run(r#"
+        fun main do
+            for i in 0..10 step 3 do i print end "\n" print
+            for i in 3..0 step -1 do i print end "\n" print
+        end
+    "#);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "0369\n321\n");
+}
+
+#[test]
+fn for_loops_leave_the_stack_as_they_found_it() {
+    let (result, output) = run(r#"
+        fun main do
+            1 2 4 let n in for i in 2..n do i print end end "\n" print
+            + println
+        end
+    "#);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "23\n3\n");
+}
+
+#[test]
+fn steps_cannot_be_zero() {
+    let errors = resolver_errors("fun main do for i in 0..3 step 0 do end end");
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "The step of a for loop must be a non-zero integer literal or constant at test.shark:1:32"
+    );
+}
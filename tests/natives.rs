@@ -0,0 +1,151 @@
+mod common;
+
+use stack_based_lang::{compile_source, Interpreter, Natives, RuntimeError, Value};
+
+use common::{capture, compile, compile_with_natives};
+
+fn natives() -> Natives {
+    let mut natives = Natives::new();
+
+    natives
+        .register("triple", 1, 1, |_, arguments| match arguments[0] {
+            Value::I64(n) => Ok(vec![Value::I64(n * 3)]),
+            _ => Err("expected an integer".to_string()),
+        })
+        .unwrap();
+    natives
+        .register("greeting", 0, 1, |interpreter, _| {
+            Ok(vec![interpreter.new_string("hello")])
+        })
+        .unwrap();
+    natives
+        .register("divmod", 2, 2, |_, arguments| match arguments[..] {
+            [Value::I64(a), Value::I64(b)] if b != 0 => {
+                Ok(vec![Value::I64(a / b), Value::I64(a % b)])
+            }
+            _ => Err("expected a non-zero divisor".to_string()),
+        })
+        .unwrap();
+
+    natives
+}
+
+fn run(source: &str) -> (Result<(), RuntimeError>, String) {
+    capture(Interpreter::new(compile_with_natives(source, &natives())))
+}
+
+#[test]
+fn scripts_call_natives() {
+    let (result, output) = run("
+        fun main do
+            14 triple println
+            greeting println
+            17 5 divmod println println
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "42\nhello\n2\n3\n");
+}
+
+#[test]
+fn native_errors_can_be_caught() {
+    let (result, output) = run(r#"
+        fun main do
+            try "a" triple catch .kind end println
+        end
+    "#);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "NativeError\n");
+
+    let (result, _) = run("fun main do 1 0 divmod drop drop end");
+    let error = result.expect_err("the native should fail");
+
+    match error.cause() {
+        RuntimeError::Native { name, message, .. } => {
+            assert_eq!(name, "divmod");
+            assert_eq!(message, "expected a non-zero divisor");
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn natives_must_be_registered() {
+    let result = compile_source(
+        "fun main do 1 triple drop end",
+        "test.shark",
+        true,
+        &Natives::new(),
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn signatures_are_checked_against_natives() {
+    let result = compile_source(
+        "fun wrong (n -> n) do triple triple drop end fun main do end",
+        "test.shark",
+        true,
+        &natives(),
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn hosts_call_script_functions() {
+    let program = compile_with_natives(
+        "fun sextuple (n -> n) do triple 2 * end fun main do end",
+        &natives(),
+    );
+    let mut interpreter = Interpreter::new(program);
+
+    interpreter.push(Value::I64(7));
+    assert!(interpreter.call("sextuple").is_ok());
+    assert!(matches!(interpreter.pop(), Some(Value::I64(42))));
+
+    interpreter.push(Value::I64(1));
+    assert!(interpreter.call("sextuple").is_ok());
+    assert!(matches!(interpreter.pop(), Some(Value::I64(6))));
+}
+
+#[test]
+fn calling_an_unknown_function_fails() {
+    let mut interpreter = Interpreter::new(compile("fun main do end"));
+
+    assert!(matches!(
+        interpreter.call("missing"),
+        Err(RuntimeError::UnknownFunction(name)) if name == "missing"
+    ));
+}
+
+#[test]
+fn builtin_names_cannot_be_registered() {
+    let mut natives = Natives::new();
+
+    for name in ["dup", "+", "if", "println extra", ""] {
+        let result = natives.register(name, 0, 0, |_, _| Ok(Vec::new()));
+        assert!(result.is_err(), "`{}` was registered", name);
+    }
+}
+
+#[test]
+fn functions_cannot_take_the_name_of_a_native() {
+    let result = compile_source(
+        "fun triple (n -> n) do 3 * end fun main do end",
+        "test.shark",
+        true,
+        &natives(),
+    );
+
+    match result {
+        Ok(_) => panic!("`triple` was redeclared"),
+        Err(error) => assert_eq!(
+            error.to_string(),
+            "Function `triple` has the name of a native word at test.shark:1:1"
+        ),
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{resolver_errors, run};
+
+#[test]
+fn nested_functions_are_called_from_their_enclosing_function() {
+    let (result, output) = run("
+        fun sum-of-squares (a b -> n) do
+            fun square (n -> n) do dup * end
+            square swap square +
+        end
+
+        fun main do 3 4 sum-of-squares println end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "25\n");
+}
+
+#[test]
+fn nested_functions_shadow_top_level_ones() {
+    let (result, output) = run("
+        fun double (n -> n) do 2 * end
+
+        fun quadruple (n -> n) do
+            fun double (n -> n) do 4 * end
+            double
+        end
+
+        fun main do 1 quadruple println 1 double println end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "4\n2\n");
+}
+
+#[test]
+fn nested_functions_see_those_of_enclosing_functions() {
+    let (result, output) = run("
+        fun outer (n -> n) do
+            fun increment (n -> n) do 1 + end
+            fun twice (n -> n) do
+                fun again (n -> n) do increment end
+                increment again
+            end
+            twice
+        end
+
+        fun main do 1 outer println end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "3\n");
+}
+
+#[test]
+fn nested_functions_are_not_visible_outside() {
+    let errors = resolver_errors(
+        "
+        fun outer do
+            fun inner do end
+        end
+
+        fun main do inner end
+    ",
+    );
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "Unknown identifier `inner` at test.shark:6:21"
+    );
+}
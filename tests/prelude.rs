@@ -0,0 +1,64 @@
+mod common;
+
+use stack_based_lang::{compile_source, Natives};
+
+use common::run;
+
+#[test]
+fn the_prelude_is_available_without_imports() {
+    let (result, output) = run("
+        fun main do
+            -3 abs println
+            2 10 pow println
+            1 5 range [ square ] map [ + ] 0 swap fold println
+        end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "3\n1024\n30\n");
+}
+
+#[test]
+fn prelude_functions_can_be_qualified() {
+    let (result, output) = run("fun main do 4 std::math::square std::strings::println end");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "16\n");
+}
+
+#[test]
+fn user_functions_shadow_the_prelude() {
+    let (result, output) = run("
+        fun square (n -> n) do 2 * end
+        fun main do 4 square println 4 std::math::square println end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "8\n16\n");
+}
+
+#[test]
+fn failed_assertions_stop_the_program() {
+    let (result, _) = run("fun main do 1 2 assert-equal end");
+
+    let error = result.expect_err("the assertion should fail");
+    assert!(error
+        .to_string()
+        .starts_with("assertion failed: expected 2, got 1"));
+}
+
+#[test]
+fn the_prelude_can_be_left_out() {
+    let error = compile_source(
+        "fun main do 1 println end",
+        "test.shark",
+        false,
+        &Natives::new(),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Unknown identifier `println` at test.shark:1:15"
+    );
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use stack_based_lang::{Interpreter, Profiler};
+
+use common::{compile, Output};
+
+const PROGRAM: &str = "fun inner (n -> n) do
+    1 +
+end
+
+fun outer (n -> n) do
+    inner inner
+end
+
+fun main do
+    0 outer outer drop
+end
+";
+
+/// Runs `source` with a profiler and hands it back.
+fn profile(source: &str) -> Interpreter {
+    let mut interpreter = Interpreter::new(compile(source))
+        .with_output(Output::default())
+        .with_profiler(Profiler::new());
+
+    interpreter.interpret().unwrap();
+    interpreter
+}
+
+#[test]
+fn folded_stacks_count_the_instructions_under_each_chain_of_calls() {
+    let interpreter = profile(PROGRAM);
+
+    let mut folded = Vec::new();
+    interpreter
+        .profiler()
+        .unwrap()
+        .write_folded(&mut folded)
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(folded).unwrap(),
+        "main 5\nmain;outer 6\nmain;outer;inner 12\n"
+    );
+}
+
+#[test]
+fn functions_are_charged_for_their_own_and_their_callees_instructions() {
+    let interpreter = profile(PROGRAM);
+
+    let functions = interpreter
+        .profiler()
+        .unwrap()
+        .functions()
+        .into_iter()
+        .map(|(name, cost)| {
+            (
+                name,
+                cost.exclusive.instructions,
+                cost.inclusive.instructions,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        functions,
+        [
+            ("inner".to_string(), 12, 12),
+            ("outer".to_string(), 6, 18),
+            ("main".to_string(), 5, 23)
+        ]
+    );
+}
+
+#[test]
+fn recursive_calls_are_charged_once_per_instruction() {
+    let interpreter = profile(
+        "
+        fun countdown (n -> n) do dup 0 > if 1 - countdown end end
+        fun main do 3 countdown drop end
+        ",
+    );
+
+    let functions = interpreter.profiler().unwrap().functions();
+    let (_, countdown) = functions
+        .iter()
+        .find(|(name, _)| name == "countdown")
+        .unwrap();
+
+    assert_eq!(
+        countdown.exclusive.instructions,
+        countdown.inclusive.instructions
+    );
+}
+
+#[test]
+fn lines_are_ranked_by_the_instructions_run_on_them() {
+    let interpreter = profile(PROGRAM);
+
+    let lines = interpreter.profiler().unwrap().lines();
+
+    assert_eq!(lines[0].0, "test.shark:2");
+    assert_eq!(lines[0].1.instructions, 8);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::resolver_errors;
+
+/// The messages of the errors the resolver reports for `source`.
+fn messages(source: &str) -> Vec<String> {
+    resolver_errors(source)
+        .iter()
+        .map(|error| error.to_string())
+        .collect()
+}
+
+#[test]
+fn functions_are_declared_once() {
+    assert_eq!(
+        messages("fun f do end fun f do end fun main do end"),
+        ["Function `f` is declared again at test.shark:1:14, it was first declared at test.shark:1:1"]
+    );
+}
+
+#[test]
+fn programs_need_a_main_function() {
+    assert_eq!(
+        messages("fun f do end"),
+        ["No `main` function is declared in test.shark"]
+    );
+}
+
+#[test]
+fn main_cannot_be_called() {
+    assert_eq!(
+        messages("fun main do main end"),
+        ["Unexpected call to main at test.shark:1:13"]
+    );
+}
+
+#[test]
+fn every_unknown_identifier_is_reported_at_once() {
+    assert_eq!(
+        messages("fun main do g 1 + h end"),
+        [
+            "Unknown identifier `g` at test.shark:1:13",
+            "Unknown identifier `h` at test.shark:1:19"
+        ]
+    );
+}
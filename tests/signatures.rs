@@ -0,0 +1,66 @@
+mod common;
+
+use stack_based_lang::ResolverError;
+
+use common::{resolver_errors, run};
+
+#[test]
+fn functions_run_with_matching_signatures() {
+    let (result, output) = run("
+        fun add3 (a b c -> r) do + + end
+        fun main do 1 2 3 add3 println end
+    ");
+
+    assert!(result.is_ok());
+    assert_eq!(output, "6\n");
+}
+
+#[test]
+fn bodies_must_match_their_signature() {
+    let errors = resolver_errors(
+        "
+        fun add3 (a b c -> r) do + end
+        fun main do 1 2 3 add3 drop end
+    ",
+    );
+
+    assert!(matches!(
+        &errors[..],
+        [ResolverError::SignatureMismatch { name, .. }] if name == "add3"
+    ));
+}
+
+#[test]
+fn bodies_cannot_consume_more_than_declared() {
+    let errors = resolver_errors(
+        "
+        fun twice (n -> n) do + 2 * end
+        fun main do 1 twice drop end
+    ",
+    );
+
+    assert!(matches!(
+        &errors[..],
+        [ResolverError::SignatureMismatch { name, .. }] if name == "twice"
+    ));
+}
+
+#[test]
+fn branches_must_leave_the_same_depth() {
+    let errors = resolver_errors("fun main do true if 1 else 1 2 end drop end");
+
+    assert!(matches!(
+        &errors[..],
+        [ResolverError::BranchEffectMismatch { .. }]
+    ));
+}
+
+#[test]
+fn loop_bodies_must_be_balanced() {
+    let errors = resolver_errors("fun main do 0 while dup 10 < do 1 dup + end drop end");
+
+    assert!(matches!(
+        &errors[..],
+        [ResolverError::UnbalancedLoop { .. }]
+    ));
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use stack_based_lang::{compile_source, Natives};
+
+use common::run;
+
+fn compile_error(source: &str) -> String {
+    match compile_source(source, "test.shark", true, &Natives::new()) {
+        Ok(_) => panic!("expected `{}` not to compile", source),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn types_are_declared_at_the_top_level() {
+    assert_eq!(
+        compile_error("fun main do struct P x end end"),
+        "Type `P` must be declared at the top level at test.shark:1:13"
+    );
+    assert_eq!(
+        compile_error("fun main do enum E A | B end end"),
+        "Type `E` must be declared at the top level at test.shark:1:13"
+    );
+}
+
+#[test]
+fn globals_cannot_take_the_name_of_a_type() {
+    assert_eq!(
+        compile_error("struct P x end const P 1 end fun main do end"),
+        "`P` is already declared at test.shark:1:16"
+    );
+    assert_eq!(
+        compile_error("enum E A | B end var A fun main do end"),
+        "`A` is already declared at test.shark:1:18"
+    );
+}
+
+#[test]
+fn structs_are_built_read_and_updated_by_field() {
+    let (result, output) = run(r#"
+        struct Point x y end
+
+        fun main do
+            1 2 Point
+            dup .x println
+            10 .x! "origin" .y!
+            println
+        end
+    "#);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "1\nPoint { x: 10, y: \"origin\" }\n");
+}
+
+#[test]
+fn fields_are_declared_once() {
+    assert_eq!(
+        compile_error("struct P x x end fun main do end"),
+        "Field `x` is declared more than once in struct `P` at test.shark:1:1"
+    );
+}
+
+#[test]
+fn fields_must_belong_to_a_struct() {
+    assert_eq!(
+        compile_error("struct P x end fun main do 1 P .y drop end"),
+        "No struct has a field named `y` at test.shark:1:32"
+    );
+}
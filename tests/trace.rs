@@ -0,0 +1,72 @@
+mod common;
+
+use std::io::BufWriter;
+
+use stack_based_lang::{Interpreter, Tracer};
+
+use common::{compile, Output};
+
+const SQUARES: &str = "
+fun sum-of-squares (a b -> n) do
+    fun square (n -> n) do
+        dup *
+    end
+
+    square swap square +
+end
+
+fun main do
+    3 4 sum-of-squares println
+end
+";
+
+/// Runs `SQUARES` with `tracer` writing to the returned output.
+fn trace(functions: Vec<String>) -> String {
+    let trace = Output::default();
+    let tracer = Tracer::new(trace.clone()).with_functions(functions);
+    let mut interpreter = Interpreter::new(compile(SQUARES))
+        .with_output(Output::default())
+        .with_tracer(tracer);
+
+    interpreter.interpret().unwrap();
+    trace.contents()
+}
+
+#[test]
+fn tracing_logs_every_instruction_with_its_function() {
+    let trace = trace(Vec::new());
+
+    assert!(trace.lines().any(|line| line.starts_with("[main]")));
+    assert!(trace
+        .lines()
+        .any(|line| line.starts_with("[sum-of-squares]")));
+    assert!(trace
+        .lines()
+        .any(|line| line.starts_with("[sum-of-squares/square]")
+            && line.ends_with("[16, 3, 3] -> [16, 9]")));
+}
+
+#[test]
+fn nested_functions_can_be_traced_by_their_own_name() {
+    let trace = trace(vec!["square".to_string()]);
+
+    assert!(!trace.is_empty());
+    assert!(trace
+        .lines()
+        .all(|line| line.starts_with("[sum-of-squares/square]")));
+}
+
+#[test]
+fn the_trace_is_flushed_once_the_program_stops() {
+    let trace = Output::default();
+    let tracer = Tracer::new(BufWriter::new(trace.clone()));
+    let mut interpreter = Interpreter::new(compile(SQUARES))
+        .with_output(Output::default())
+        .with_tracer(tracer);
+
+    interpreter.interpret().unwrap();
+
+    // The interpreter, and so the buffer, is still alive here.
+    assert!(trace.contents().contains("[main]"));
+    drop(interpreter);
+}